@@ -0,0 +1,704 @@
+/// Why an [`Array`] operation couldn't be done
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayError {
+    /// Every slot is taken, the array has to [`Array::extend`] before anything else fits
+    CapacityExhausted,
+    /// All of the old data has been copied over already
+    NoOldDataRemaining,
+    /// The old data of the last resize is still being copied, nothing can move before that's done
+    StillCopying,
+}
+
+impl std::fmt::Display for ArrayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArrayError::CapacityExhausted => write!(f, "capacity exhausted"),
+            ArrayError::NoOldDataRemaining => write!(f, "no old data remaining"),
+            ArrayError::StillCopying => write!(f, "still copying"),
+        }
+    }
+}
+
+impl std::error::Error for ArrayError {}
+
+/// When the old data gets copied over after a resize
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyModel {
+    /// One element at a time through [`Array::append_old_data`], like incremental rehashing
+    Incremental,
+    /// Everything right away as part of the resize, like a `realloc` in `Vec`
+    Instant,
+}
+
+impl CopyModel {
+    pub fn from_name(name: &str) -> Option<CopyModel> {
+        match name {
+            "incremental" => Some(CopyModel::Incremental),
+            "instant" => Some(CopyModel::Instant),
+            _ => None,
+        }
+    }
+}
+
+/// The modelled dynamic array
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Array {
+    pub growth: f64,
+    pub old_data_size: usize,
+    pub size: usize,
+    pub capacity: usize,
+    pub hard_limit: Option<usize>,
+    pub old_data_appended: usize, // Track how much old data has been appended back,
+    pub resizes: usize,
+    /// Resizes that didn't have to copy anything, see [`Array::extend_in_place`]
+    pub inplace_resizes: usize,
+    pub copy_operations: usize,
+    /// Most operations a single append would've taken if resizes copied everything right away,
+    /// the resize and the append itself plus every copied element
+    pub worst_case_append: usize,
+    /// Shrink once `size` drops below `capacity / shrink_threshold`, never shrinks when [`None`]
+    pub shrink_threshold: Option<f64>,
+    pub shrinks: usize,
+    /// Bytes taken by a single element, only used for showing sizes in bytes
+    pub element_size: usize,
+    /// Nanoseconds copying a single element takes, only used for estimating the time spent copying
+    pub copy_cost: Option<f64>,
+    pub copy_model: CopyModel,
+    /// Capacity the last [`Array::reserve`] made room for, the slots from `size` up to it are reserved.
+    /// Any other resize or shrink sets it back to 0
+    pub reserved: usize,
+}
+
+impl Array {
+    pub fn new(growth: f64, hard_limit: Option<usize>) -> Array {
+        Array {
+            growth,
+            old_data_size: 0,
+            size: 0,
+            capacity: 1,
+            hard_limit,
+            old_data_appended: 0,
+            copy_operations: 0,
+            worst_case_append: 1,
+            resizes: 0,
+            inplace_resizes: 0,
+            shrink_threshold: None,
+            shrinks: 0,
+            element_size: 1,
+            copy_cost: None,
+            copy_model: CopyModel::Incremental,
+            reserved: 0,
+        }
+    }
+
+    /// Starts out with room for `capacity` elements (capped at the hard limit) like `Vec::with_capacity`.
+    /// Zero is fine too, the first resize then allocates a single slot
+    pub fn with_initial_capacity(mut self, capacity: usize) -> Array {
+        self.capacity = match self.hard_limit {
+            Some(limit) => capacity.min(limit),
+            None => capacity,
+        };
+        self
+    }
+
+    pub fn with_element_size(mut self, element_size: usize) -> Array {
+        self.element_size = element_size;
+        self
+    }
+
+    pub fn with_shrink_threshold(mut self, shrink_threshold: Option<f64>) -> Array {
+        self.shrink_threshold = shrink_threshold;
+        self
+    }
+
+    pub fn with_copy_cost(mut self, copy_cost: Option<f64>) -> Array {
+        self.copy_cost = copy_cost;
+        self
+    }
+
+    pub fn with_copy_model(mut self, copy_model: CopyModel) -> Array {
+        self.copy_model = copy_model;
+        self
+    }
+
+    /// Will return [`ArrayError::CapacityExhausted`] if capacity is not enough to hold the new data
+    /// Will return [`Ok(usize)`] if the data was added successfully and usize is the address of the new data
+    pub fn grow(&mut self) -> Result<usize, ArrayError> {
+        let new_size = self.size + 1;
+        if new_size > self.capacity {
+            return Err(ArrayError::CapacityExhausted);
+        }
+        self.size = new_size;
+        Ok(self.size)
+    }
+    
+    /// Capacity the next resize grows into. An empty allocation stays empty no matter the growth factor,
+    /// so that grows to a single slot like the first push into a `Vec` does. Factors barely above 1 can
+    /// round back down to the same capacity, it always grows by at least one slot so they still make progress
+    pub fn next_capacity(&self) -> usize {
+        let capacity = ((self.capacity as f64 * self.growth).ceil() as usize).max(self.capacity + 1);
+        if let Some(limit) = self.hard_limit {
+            if capacity > limit {
+                return limit;
+            }
+        }
+        capacity
+    }
+
+    /// Whether a resize would make any room, it won't once the capacity is at the hard limit
+    pub fn can_extend(&self) -> bool {
+        self.next_capacity() > self.capacity
+    }
+
+    pub fn extend(&mut self) {
+        self.extend_to(self.next_capacity());
+    }
+
+    /// Moves everything into a new block of exactly `capacity` slots, for following what a real allocator
+    /// decided instead of the growth factor. The old data gets copied over like after [`Array::extend`]
+    pub fn extend_to(&mut self, capacity: usize) {
+        self.resizes += 1;
+        self.reserved = 0;
+        self.old_data_size = self.size;
+        self.capacity = capacity;
+        self.old_data_appended = 0;
+        self.worst_case_append = self.worst_case_append.max(self.old_data_size + 2);
+        if self.copy_model == CopyModel::Instant {
+            self.copy_remaining();
+        }
+    }
+
+    /// Grows like [`Array::extend`] but as if the allocator managed to grow the block where it is,
+    /// so there's no old data to copy over
+    pub fn extend_in_place(&mut self) {
+        self.extend_in_place_to(self.next_capacity());
+    }
+
+    /// [`Array::extend_in_place`] into exactly `capacity` slots, like [`Array::extend_to`]
+    pub fn extend_in_place_to(&mut self, capacity: usize) {
+        self.resizes += 1;
+        self.reserved = 0;
+        self.inplace_resizes += 1;
+        self.old_data_size = 0;
+        self.capacity = capacity;
+        self.old_data_appended = 0;
+        self.worst_case_append = self.worst_case_append.max(2);
+    }
+
+    /// Removes the last element and shrinks the capacity if the load dropped below the shrink threshold
+    /// Will return [`None`] if the array is already empty, otherwise the new size
+    pub fn pop(&mut self) -> Option<usize> {
+        if self.size == 0 {
+            return None;
+        }
+        self.size -= 1;
+        if let Some(threshold) = self.shrink_threshold {
+            if (self.size as f64) < self.capacity as f64 / threshold {
+                self.shrink();
+            }
+        }
+        Some(self.size)
+    }
+
+    /// Reallocates into a block `growth` times smaller (but still big enough for every element).
+    /// The retained elements get copied over through [`Array::append_old_data`] just like after [`Array::extend`]
+    pub fn shrink(&mut self) {
+        let new_capacity = ((self.capacity as f64 / self.growth).floor() as usize).max(self.size).max(1);
+        if new_capacity >= self.capacity {
+            return;
+        }
+        self.shrinks += 1;
+        self.reserved = 0;
+        self.old_data_size = self.size;
+        self.capacity = new_capacity;
+        self.old_data_appended = 0;
+        if self.copy_model == CopyModel::Instant {
+            self.copy_remaining();
+        }
+    }
+
+    /// Copies per append a growth factor of `growth` settles on in the long run, `1 / (growth - 1)`.
+    /// [`None`] when the factor is too small to ever amortize
+    pub fn theoretical_copies_per_append(&self) -> Option<f64> {
+        (self.growth > 1.0).then(|| 1.0 / (self.growth - 1.0))
+    }
+
+    /// Bytes allocated for the whole capacity
+    pub fn allocated_bytes(&self) -> usize {
+        self.capacity * self.element_size
+    }
+
+    /// Bytes taken by the elements actually stored
+    pub fn used_bytes(&self) -> usize {
+        self.size * self.element_size
+    }
+
+    /// Bytes moved by every copy so far
+    pub fn copied_bytes(&self) -> usize {
+        self.copy_operations * self.element_size
+    }
+
+    /// Time all the copies so far would've taken at [`Array::copy_cost`] per element, [`None`] without a cost
+    pub fn estimated_copy_time(&self) -> Option<std::time::Duration> {
+        self.copy_cost.map(|cost| std::time::Duration::from_secs_f64(self.copy_operations as f64 * cost / 1e9))
+    }
+
+    /// Bytes ever copied per byte stored, 0 for an empty array. Settles around `1 / (growth - 1)`,
+    /// so conservative growth factors pay for their tighter fit by copying everything many times over
+    pub fn copy_amplification(&self) -> f64 {
+        if self.size == 0 {
+            return 0.0;
+        }
+        self.copied_bytes() as f64 / self.used_bytes() as f64
+    }
+
+    /// Fraction of the capacity taken by live data, counting the part of the old data copied so far.
+    ///
+    /// That's `((size - old_data_size) + old_data_appended) / capacity`: the elements appended since the
+    /// resize plus the old elements already copied over. Old elements still waiting to be copied live in
+    /// the previous block and don't count, so the efficiency dips right after a resize and climbs back
+    /// as the copy catches up
+    pub fn memory_efficiency(&self) -> f64 {
+        if self.capacity == 0 {
+            return 0.0;
+        }
+        ((self.size as f64 - self.old_data_size as f64) + self.old_data_appended as f64) / (self.capacity as f64)
+    }
+
+    /// Copies all of the old data left over in one go, returns how many elements that was
+    pub fn copy_remaining(&mut self) -> usize {
+        let remaining = self.old_data_size - self.old_data_appended;
+        self.copy_operations += remaining;
+        self.old_data_appended = self.old_data_size;
+        remaining
+    }
+
+    /// Appends one element, first resizing with `resize` (usually [`Array::extend`]) when the array is full.
+    /// A full array that's still copying the old data after the last resize has to wait for the copy to finish,
+    /// that's [`Append::Waiting`] and nothing gets appended.
+    /// Will return [`ArrayError::CapacityExhausted`] if the array is full and can't grow any further
+    pub fn append_with(&mut self, resize: impl FnOnce(&mut Array)) -> Result<Append, ArrayError> {
+        match self.grow() {
+            Ok(address) => Ok(Append::Appended(address)),
+            Err(_) if !self.can_extend() => Err(ArrayError::CapacityExhausted),
+            Err(_) if self.old_data_appended < self.old_data_size => Ok(Append::Waiting),
+            Err(_) => {
+                resize(self);
+                self.grow().map(Append::Resized)
+            }
+        }
+    }
+
+    /// Makes room for `additional` more elements like `Vec::reserve`, resizing into exactly `size + additional` slots
+    /// when they don't fit yet. That's a resize like any other, the old data gets copied over.
+    /// Will return whether it resized, [`ArrayError::CapacityExhausted`] if the room would go past the hard limit
+    /// or [`ArrayError::StillCopying`] while the last resize is still being copied
+    pub fn reserve(&mut self, additional: usize) -> Result<bool, ArrayError> {
+        let needed = self.size + additional;
+        if needed <= self.capacity {
+            return Ok(false);
+        }
+        if self.hard_limit.is_some_and(|limit| needed > limit) {
+            return Err(ArrayError::CapacityExhausted);
+        }
+        if self.old_data_appended < self.old_data_size {
+            return Err(ArrayError::StillCopying);
+        }
+        self.extend_to(needed);
+        self.reserved = needed;
+        Ok(true)
+    }
+
+    /// Appends `n` elements at once like `Vec::extend_from_slice`, with a single resize when they don't fit.
+    /// The resize grows by at least the growth factor and to at least `size + n`, see [`Array::grow_by_with`]
+    pub fn grow_by(&mut self, n: usize) -> Result<Append, ArrayError> {
+        self.grow_by_with(n, |array, needed| array.extend_to(array.next_capacity().max(needed)))
+    }
+
+    /// [`Array::append_with`] for `n` elements at once, `resize` gets the capacity they need and has to
+    /// make at least that much room. The address is the one of the last of them.
+    /// Will return [`ArrayError::CapacityExhausted`] if they'd go past the hard limit, then nothing gets appended
+    pub fn grow_by_with(&mut self, n: usize, resize: impl FnOnce(&mut Array, usize)) -> Result<Append, ArrayError> {
+        let needed = self.size + n;
+        if needed <= self.capacity {
+            self.size = needed;
+            return Ok(Append::Appended(self.size));
+        }
+        if self.hard_limit.is_some_and(|limit| needed > limit) {
+            return Err(ArrayError::CapacityExhausted);
+        }
+        if self.old_data_appended < self.old_data_size {
+            return Ok(Append::Waiting);
+        }
+        resize(self, needed);
+        if needed > self.capacity {
+            return Err(ArrayError::CapacityExhausted);
+        }
+        self.size = needed;
+        Ok(Append::Resized(self.size))
+    }
+
+    /// Advances by one step, which is what the visualizer does every frame with the default options:
+    ///
+    /// 1. append one element through [`Array::append_with`] and [`Array::extend`], resizing when full
+    ///    (nothing gets appended while a full array is still copying)
+    /// 2. copy one element of the old data over with [`Array::append_old_data`], if any is left
+    ///
+    /// The append costs one operation, two with the resize, and the copy one more. With [`CopyModel::Instant`]
+    /// the resize copies everything itself, those copies count towards the step doing the resize
+    pub fn step(&mut self) -> Snapshot {
+        let copies = self.copy_operations;
+        let mut operations = match self.append_with(Array::extend) {
+            Ok(Append::Appended(_)) => 1,
+            Ok(Append::Resized(_)) => 2 + self.copy_operations - copies,
+            Ok(Append::Waiting) | Err(_) => 0,
+        };
+        if self.append_old_data().is_ok() {
+            operations += 1;
+        }
+        Snapshot {
+            size: self.size,
+            capacity: self.capacity,
+            resizes: self.resizes,
+            copy_operations: self.copy_operations,
+            efficiency: self.memory_efficiency(),
+            operations,
+        }
+    }
+
+    /// Steps through the array one [`Array::step`] at a time, like `array.simulate().take(100000)`.
+    /// Ends once a step can't do anything, which without a hard limit is never
+    pub fn simulate(self) -> Simulate {
+        Simulate { array: self }
+    }
+
+    /// Checks the relations between the counters that always have to hold, for catching bugs in the model.
+    /// Will return which one is broken if any
+    pub fn check_invariants(&self) -> Result<(), String> {
+        if self.size > self.capacity {
+            return Err(format!("size {} is bigger than the capacity {}", self.size, self.capacity));
+        }
+        if self.old_data_appended > self.old_data_size {
+            return Err(format!("{} elements of old data copied but there's only {}", self.old_data_appended, self.old_data_size));
+        }
+        if self.old_data_size > self.capacity {
+            return Err(format!("{} elements of old data don't fit the capacity {}", self.old_data_size, self.capacity));
+        }
+        Ok(())
+    }
+
+    /// Copies the next element of old data over into the new allocation
+    /// Will return [`ArrayError::NoOldDataRemaining`] once everything has been copied
+    pub fn append_old_data(&mut self) -> Result<usize, ArrayError> {
+        if self.old_data_appended < self.old_data_size {
+            self.copy_operations += 1;
+            self.old_data_appended += 1;
+            Ok(self.old_data_appended)
+        } else {
+            Err(ArrayError::NoOldDataRemaining)
+        }
+    }
+}
+
+/// What [`Array::append_with`] did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Append {
+    /// Stored at this address, like [`Array::grow`] returns
+    Appended(usize),
+    /// Resized and then stored at this address
+    Resized(usize),
+    /// Full and still copying, nothing was appended
+    Waiting,
+}
+
+/// The array after one [`Array::step`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Snapshot {
+    pub size: usize,
+    pub capacity: usize,
+    pub resizes: usize,
+    pub copy_operations: usize,
+    /// [`Array::memory_efficiency`]
+    pub efficiency: f64,
+    /// Operations the step took, 0 when it couldn't do anything
+    pub operations: usize,
+}
+
+/// Iterator of [`Array::simulate`]
+#[derive(Debug, Clone, Copy)]
+pub struct Simulate {
+    array: Array,
+}
+
+impl Simulate {
+    /// The array as of the last step
+    pub fn array(&self) -> &Array {
+        &self.array
+    }
+}
+
+impl Iterator for Simulate {
+    type Item = Snapshot;
+
+    fn next(&mut self) -> Option<Snapshot> {
+        let snapshot = self.array.step();
+        (snapshot.operations > 0).then_some(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Appends one element, finishing any pending copy and resizing first when the array is full
+    fn push(array: &mut Array) {
+        if array.grow().is_err() {
+            while array.append_old_data().is_ok() {}
+            array.extend();
+            array.grow().unwrap();
+        }
+    }
+
+    #[test]
+    fn grow_fails_exactly_when_full() {
+        let mut array = Array::new(2.0, None);
+        for _ in 0..10 {
+            while array.size < array.capacity {
+                let address = array.size + 1;
+                assert_eq!(array.grow(), Ok(address));
+            }
+            assert_eq!(array.grow(), Err(ArrayError::CapacityExhausted));
+            array.extend();
+        }
+    }
+
+    #[test]
+    fn extend_multiplies_and_caps_capacity() {
+        let mut array = Array::new(1.5, Some(100));
+        let mut expected = 1;
+        while expected < 100 {
+            array.extend();
+            expected = ((expected as f64 * 1.5).ceil() as usize).min(100);
+            assert_eq!(array.capacity, expected);
+        }
+        array.extend();
+        assert_eq!(array.capacity, 100);
+    }
+
+    #[test]
+    fn old_data_is_copied_exactly_once() {
+        let mut array = Array::new(2.0, None);
+        for _ in 0..8 {
+            push(&mut array);
+        }
+        while array.append_old_data().is_ok() {}
+        array.grow().unwrap_err();
+        let copies_before = array.copy_operations;
+        array.extend();
+        assert_eq!(array.old_data_size, 8);
+        for copied in 1..=8 {
+            assert_eq!(array.append_old_data(), Ok(copied));
+            assert_eq!(array.copy_operations, copies_before + copied);
+        }
+        assert_eq!(array.append_old_data(), Err(ArrayError::NoOldDataRemaining));
+        assert_eq!(array.copy_operations, copies_before + 8);
+    }
+
+    #[test]
+    fn worst_case_append_is_the_biggest_copy() {
+        let mut array = Array::new(2.0, None);
+        for _ in 0..100 {
+            push(&mut array);
+        }
+        // the last resize went from 64 to 128 and had to copy all 64 elements
+        assert_eq!(array.worst_case_append, 64 + 2);
+    }
+
+    #[test]
+    fn zero_initial_capacity_grows_to_one() {
+        let mut array = Array::new(2.0, None).with_initial_capacity(0);
+        assert_eq!(array.memory_efficiency(), 0.0);
+        assert_eq!(array.grow(), Err(ArrayError::CapacityExhausted));
+        array.extend();
+        assert_eq!(array.capacity, 1);
+        assert_eq!(array.grow(), Ok(1));
+    }
+
+    #[test]
+    fn initial_capacity_saves_resizes() {
+        let mut naive = Array::new(2.0, None);
+        let mut reserved = Array::new(2.0, None).with_initial_capacity(64);
+        for _ in 0..64 {
+            push(&mut naive);
+            push(&mut reserved);
+        }
+        assert_eq!(naive.resizes, 6);
+        assert_eq!(reserved.resizes, 0);
+        assert_eq!(reserved.copy_operations, 0);
+    }
+
+    #[test]
+    fn fixed_capacity_cant_extend() {
+        let mut array = Array::new(2.0, Some(8)).with_initial_capacity(8);
+        assert!(!array.can_extend());
+        for _ in 0..8 {
+            array.grow().unwrap();
+        }
+        assert_eq!(array.grow(), Err(ArrayError::CapacityExhausted));
+        assert_eq!(array.memory_efficiency(), 1.0);
+        assert!(Array::new(2.0, Some(8)).can_extend());
+    }
+
+    #[test]
+    fn tiny_growth_factors_still_make_progress() {
+        for growth in [1.000_000_1, 1.01] {
+            let mut array = Array::new(growth, None);
+            for _ in 0..1000 {
+                push(&mut array);
+            }
+            assert_eq!(array.size, 1000);
+        }
+    }
+
+    #[test]
+    fn copies_are_amortized_linear() {
+        let mut array = Array::new(2.0, None);
+        for n in 1..=100_000 {
+            push(&mut array);
+            assert!(array.copy_operations <= 2 * n, "{} copies for {} appends", array.copy_operations, n);
+        }
+    }
+
+    #[test]
+    fn full_arrays_copied_the_theoretical_amount() {
+        for growth in [1.5, 2.0, 3.0] {
+            let mut array = Array::new(growth, None);
+            while array.capacity < 100_000 {
+                push(&mut array);
+            }
+            while array.size < array.capacity {
+                push(&mut array);
+            }
+            while array.append_old_data().is_ok() {}
+            let measured = array.copy_operations as f64 / array.size as f64;
+            let theoretical = array.theoretical_copies_per_append().unwrap();
+            assert!((measured - theoretical).abs() < 0.05 * theoretical, "{} copies per append for {}", measured, growth);
+        }
+        assert_eq!(Array::new(1.0, None).theoretical_copies_per_append(), None);
+    }
+
+    #[test]
+    fn small_factors_amplify_copies() {
+        let amplification = |growth: f64| {
+            let mut array = Array::new(growth, None).with_element_size(8);
+            for _ in 0..100_000 {
+                push(&mut array);
+            }
+            array.copy_amplification()
+        };
+        assert_eq!(Array::new(2.0, None).copy_amplification(), 0.0);
+        assert!(amplification(2.0) <= 2.0);
+        assert!(amplification(1.1) > 5.0 * amplification(2.0));
+    }
+
+    #[test]
+    fn simulating_waits_for_the_copy_and_stops_at_the_limit() {
+        let snapshots: Vec<Snapshot> = Array::new(1.5, Some(20)).simulate().collect();
+        let last = snapshots.last().unwrap();
+        assert_eq!((last.size, last.capacity), (20, 20));
+        // every step appends or copies, and the ones that only copy are waiting on a full array
+        for pair in snapshots.windows(2) {
+            assert!(pair[1].size == pair[0].size + 1 || pair[1].copy_operations == pair[0].copy_operations + 1);
+            if pair[1].size == pair[0].size {
+                assert_eq!(pair[0].size, pair[0].capacity);
+            }
+        }
+        assert_eq!(Array::new(2.0, None).simulate().take(1000).count(), 1000);
+    }
+
+    #[test]
+    fn instant_copies_finish_with_the_resize() {
+        let mut incremental = Array::new(2.0, None);
+        let mut instant = Array::new(2.0, None).with_copy_model(CopyModel::Instant);
+        let mut spike = 0;
+        for _ in 0..1000 {
+            incremental.step();
+            let snapshot = instant.step();
+            assert_eq!(instant.old_data_appended, instant.old_data_size);
+            spike = spike.max(snapshot.operations);
+        }
+        // the copy for the resize to 1024 is in there all at once
+        assert_eq!(spike, 2 + 512);
+        while incremental.append_old_data().is_ok() {}
+        assert_eq!(instant.copy_operations, incremental.copy_operations);
+    }
+
+    #[test]
+    fn copy_time_scales_with_the_cost() {
+        let mut array = Array::new(2.0, None).with_copy_cost(Some(250.0));
+        for _ in 0..1000 {
+            push(&mut array);
+        }
+        assert_eq!(array.estimated_copy_time().unwrap().as_nanos(), array.copy_operations as u128 * 250);
+        assert_eq!(Array::new(2.0, None).estimated_copy_time(), None);
+    }
+
+    #[test]
+    fn bulk_appends_resize_once() {
+        let mut array = Array::new(2.0, Some(500));
+        assert_eq!(array.grow_by(10), Ok(Append::Resized(10)));
+        // the chunk needs more than doubling gives
+        assert_eq!((array.capacity, array.resizes), (10, 1));
+        assert_eq!(array.grow_by(5), Ok(Append::Resized(15)));
+        assert_eq!((array.capacity, array.old_data_size), (20, 10));
+        assert_eq!(array.grow_by(5), Ok(Append::Appended(20)));
+        // the next resize waits for the copy like a single append would
+        assert_eq!(array.grow_by(1), Ok(Append::Waiting));
+
+        let mut bulk = Array::new(1.5, None);
+        let mut single = Array::new(1.5, None);
+        for _ in 0..100 {
+            bulk.copy_remaining();
+            bulk.grow_by(64).unwrap();
+            for _ in 0..64 {
+                push(&mut single);
+            }
+        }
+        assert_eq!(bulk.size, single.size);
+        assert!(bulk.resizes < single.resizes);
+        assert_eq!(Array::new(2.0, Some(8)).grow_by(9), Err(ArrayError::CapacityExhausted));
+    }
+
+    #[test]
+    fn reserving_skips_the_resizes_in_between() {
+        let mut array = Array::new(2.0, None);
+        push(&mut array);
+        assert_eq!(array.reserve(999), Ok(true));
+        assert_eq!((array.capacity, array.reserved, array.resizes), (1000, 1000, 1));
+        assert_eq!(array.reserve(10), Ok(false));
+        for _ in 1..1000 {
+            push(&mut array);
+        }
+        assert_eq!(array.resizes, 1);
+        push(&mut array);
+        assert_eq!((array.resizes, array.reserved), (2, 0));
+
+        let mut copying = Array::new(2.0, Some(100));
+        copying.grow_by(3).unwrap();
+        copying.grow_by(1).unwrap();
+        assert_eq!(copying.reserve(10), Err(ArrayError::StillCopying));
+        copying.copy_remaining();
+        assert_eq!(copying.reserve(200), Err(ArrayError::CapacityExhausted));
+    }
+
+    #[test]
+    fn broken_counters_get_caught() {
+        let mut array = Array::new(1.5, None);
+        for _ in 0..100 {
+            push(&mut array);
+            array.check_invariants().unwrap();
+        }
+        array.old_data_appended = array.old_data_size + 1;
+        assert!(array.check_invariants().is_err());
+    }
+}
@@ -0,0 +1,116 @@
+//! Headless sweeps over growth factors, for comparing them without watching every run
+use std::io::Write;
+
+use crate::array::Array;
+
+/// What the benchmark table gets sorted by, best first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    /// Fewest resizes
+    Resizes,
+    /// Fewest copy operations
+    Copies,
+    /// Highest mean memory efficiency
+    Efficiency,
+}
+
+impl SortBy {
+    pub fn from_name(name: &str) -> Option<SortBy> {
+        match name {
+            "resizes" => Some(SortBy::Resizes),
+            "copies" => Some(SortBy::Copies),
+            "efficiency" => Some(SortBy::Efficiency),
+            _ => None,
+        }
+    }
+}
+
+/// Result of running one growth factor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchmarkRow {
+    pub growth: f64,
+    pub resizes: usize,
+    pub copy_operations: usize,
+    /// Mean of `size / capacity` after every append
+    pub mean_efficiency: f64,
+}
+
+/// Growth factors from `from` to `to` (inclusive, give or take float error) `step` apart
+pub fn growths(from: f64, to: f64, step: f64) -> Vec<f64> {
+    let steps = ((to - from) / step + 1e-9).floor() as usize;
+    // rounded so the labels don't end up as 1.2000000000000002
+    (0..=steps).map(|i| ((from + i as f64 * step) * 1e9).round() / 1e9).collect()
+}
+
+/// Appends `elements` elements into an array growing by `growth`. Unlike the visualizer
+/// the old data gets copied over right away on every resize, which ends up with the same totals
+pub fn run(growth: f64, elements: usize) -> BenchmarkRow {
+    let mut array = Array::new(growth, None);
+    let mut efficiency_sum = 0.0;
+    for _ in 0..elements {
+        if array.grow().is_err() {
+            while array.append_old_data().is_ok() {}
+            array.extend();
+            array.grow().expect("growth factors above 1 always make room");
+        }
+        efficiency_sum += array.size as f64 / array.capacity as f64;
+    }
+    while array.append_old_data().is_ok() {}
+    BenchmarkRow {
+        growth,
+        resizes: array.resizes,
+        copy_operations: array.copy_operations,
+        mean_efficiency: efficiency_sum / elements.max(1) as f64,
+    }
+}
+
+/// Sorts `rows` best first by `by`
+pub fn sort(rows: &mut [BenchmarkRow], by: SortBy) {
+    match by {
+        SortBy::Resizes => rows.sort_by_key(|row| row.resizes),
+        SortBy::Copies => rows.sort_by_key(|row| row.copy_operations),
+        SortBy::Efficiency => rows.sort_by(|a, b| b.mean_efficiency.total_cmp(&a.mean_efficiency)),
+    }
+}
+
+/// Writes `rows` as an aligned table
+pub fn write_table(rows: &[BenchmarkRow], out: &mut impl Write) -> std::io::Result<()> {
+    writeln!(out, "{:>8} {:>10} {:>16} {:>16}", "growth", "resizes", "copy operations", "mean efficiency")?;
+    for row in rows {
+        writeln!(out, "{:>8} {:>10} {:>16} {:>15.3}%", row.growth, row.resizes, row.copy_operations, row.mean_efficiency * 100.0)?;
+    }
+    Ok(())
+}
+
+pub fn write_csv(rows: &[BenchmarkRow], out: &mut impl Write) -> std::io::Result<()> {
+    writeln!(out, "growth,resizes,copy_operations,mean_efficiency")?;
+    for row in rows {
+        writeln!(out, "{},{},{},{}", row.growth, row.resizes, row.copy_operations, row.mean_efficiency)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep_includes_both_ends() {
+        assert_eq!(growths(1.1, 1.5, 0.1), vec![1.1, 1.2, 1.3, 1.4, 1.5]);
+        assert_eq!(growths(2.0, 2.0, 0.5), vec![2.0]);
+    }
+
+    #[test]
+    fn bigger_factors_resize_less() {
+        let mut rows = growths(1.5, 3.0, 0.5).into_iter().map(|g| run(g, 10_000)).collect::<Vec<_>>();
+        let doubling = rows[1];
+        assert_eq!(doubling.growth, 2.0);
+        // 1 -> 2 -> ... -> 16384
+        assert_eq!(doubling.resizes, 14);
+        assert_eq!(doubling.copy_operations, 16383);
+        sort(&mut rows, SortBy::Resizes);
+        assert_eq!(rows[0].growth, 3.0);
+        sort(&mut rows, SortBy::Efficiency);
+        assert_eq!(rows[0].growth, 1.5);
+    }
+}
@@ -0,0 +1,848 @@
+//! Command line handling
+use std::str::FromStr;
+
+use crate::array::CopyModel;
+use crate::benchmark::SortBy;
+use crate::theme::{Palette, Theme};
+
+/// How big the array is allowed to get
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HardLimit {
+    /// As many elements as there are cells in the grid
+    Grid,
+    Fixed(usize),
+    /// No limit at all, the run ends once the visible grid is full
+    Unlimited,
+}
+
+/// Frames `--animate-resize` takes without `--animate-frames`, a third of a second
+pub const ANIMATE_FRAMES: u64 = 20;
+
+/// Growth factors of real implementations by the name `--growth-preset` takes them
+pub const GROWTH_PRESETS: [(&str, f64); 4] = [
+    // the golden ratio, the biggest factor that lets freed blocks add up to the next allocation
+    ("golden", 1.618),
+    // libstdc++, libc++ and Rust's Vec
+    ("double", 2.0),
+    // MSVC's std::vector
+    ("msvc", 1.5),
+    // folly::fbvector
+    ("folly", 1.5),
+];
+
+/// Most growth factors `--overlay` takes, one more than there are overlay colors
+pub const MAX_OVERLAY: usize = 7;
+
+/// How the memory map is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapLayout {
+    /// A cell per slot, row by row
+    Grid,
+    /// One long bar scaled to the capacity, wrapped into thin rows
+    Linear,
+    /// A bar per allocation so far, the ones given up by resizes grayed out
+    History,
+}
+
+/// A line of the stat panel, picked with `--metrics`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Efficiency,
+    OpsThisAppend,
+    Capacity,
+    Size,
+    Growth,
+    AverageEfficiency,
+    MinimumEfficiency,
+    AverageOps,
+    TotalOperations,
+    CopyOperations,
+    Resizes,
+    CopyRate,
+    CopyProgress,
+    CopiesPerResize,
+    CopiesPerAppend,
+    WorstAppend,
+    Ticks,
+    Elapsed,
+    AllocatedBytes,
+    UsedBytes,
+    WastedBytes,
+    FreedBlocks,
+    NextResize,
+    UntilLimit,
+    CopyTime,
+    AppendsPerFrame,
+    InPlace,
+    Shrinks,
+}
+
+impl Metric {
+    /// Every metric with its name for `--metrics`, in the order the panel shows them by default
+    pub const NAMES: [(Metric, &'static str); 28] = [
+        (Metric::Efficiency, "efficiency"),
+        (Metric::OpsThisAppend, "ops"),
+        (Metric::Capacity, "capacity"),
+        (Metric::Size, "size"),
+        (Metric::Growth, "growth"),
+        (Metric::AverageEfficiency, "average-efficiency"),
+        (Metric::MinimumEfficiency, "minimum-efficiency"),
+        (Metric::AverageOps, "average-ops"),
+        (Metric::TotalOperations, "total-ops"),
+        (Metric::CopyOperations, "copies"),
+        (Metric::Resizes, "resizes"),
+        (Metric::CopyRate, "copy-rate"),
+        (Metric::CopyProgress, "copy-progress"),
+        (Metric::CopiesPerResize, "copies-per-resize"),
+        (Metric::CopiesPerAppend, "copies-per-append"),
+        (Metric::WorstAppend, "worst-append"),
+        (Metric::Ticks, "ticks"),
+        (Metric::Elapsed, "elapsed"),
+        (Metric::AllocatedBytes, "allocated"),
+        (Metric::UsedBytes, "used"),
+        (Metric::WastedBytes, "wasted"),
+        (Metric::FreedBlocks, "freed"),
+        (Metric::NextResize, "next-resize"),
+        (Metric::UntilLimit, "until-limit"),
+        (Metric::CopyTime, "copy-time"),
+        (Metric::AppendsPerFrame, "appends-per-frame"),
+        (Metric::InPlace, "in-place"),
+        (Metric::Shrinks, "shrinks"),
+    ];
+
+    pub fn all() -> Vec<Metric> {
+        Self::NAMES.iter().map(|&(metric, _)| metric).collect()
+    }
+
+    pub fn from_name(name: &str) -> Option<Metric> {
+        Self::NAMES.iter().find(|&&(_, n)| n == name).map(|&(metric, _)| metric)
+    }
+}
+
+/// Options for a single run. The growth factor can still be passed as the first bare argument
+/// like `cargo run -r -- 1.5` so the old invocations in `start.bat` keep working.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Factor the capacity gets multiplied by on every resize
+    pub growth: f64,
+    /// Sizes from which on a different growth factor is used, sorted by size. Below the first one it's [`Config::growth`]
+    pub growth_schedule: Vec<(usize, f64)>,
+    /// Size classes every resize rounds up to
+    pub tiers: Vec<usize>,
+    /// Growth factor exactly as it was typed, used for naming the recording
+    pub growth_label: String,
+    /// Name of the `--growth-preset` the growth factor came from
+    pub growth_preset: Option<&'static str>,
+    /// Where to write the final statistics JSON, stdout when [`None`]
+    pub stats_out: Option<String>,
+    /// Where to write the final memory map as SVG
+    pub svg_out: Option<String>,
+    /// Where to write the per-tick CSV metrics
+    pub csv: Option<String>,
+    /// CSV written with `--csv` to play back instead of simulating
+    pub replay: Option<String>,
+    /// Shrink the array once its load drops below `1 / shrink_threshold`, the array gets drained after filling up to show it
+    pub shrink_threshold: Option<f64>,
+    /// Growth factors to run side by side, empty when not comparing
+    pub compare: Vec<f64>,
+    /// Growth factors whose efficiency gets plotted on the same graph, only the first one is shown in the grid
+    pub overlay: Vec<f64>,
+    /// Old elements copied per frame after a resize, appends wait for the copy when set
+    pub copies_per_frame: Option<usize>,
+    /// Whether resizes copy everything right away or one element per tick
+    pub copy_model: CopyModel,
+    /// JSON lines file for every resize
+    pub log_events: Option<String>,
+    /// Run a real `Vec` along with the model and plot both capacities
+    pub compare_std: bool,
+    /// Draw the blocks freed by resizes as a strip under the stat panel
+    pub show_freelist: bool,
+    /// Appends per frame at the start, can be changed with `+` and `-` while running
+    pub appends_per_frame: usize,
+    /// Bytes per element for the byte statistics
+    pub element_size: usize,
+    /// Nanoseconds per copied element for the estimated copy time
+    pub element_cost: Option<f64>,
+    /// Chance of a resize growing the block in place instead of copying
+    pub inplace_prob: f64,
+    /// Mean appends per frame when they arrive at random instead of at a steady rate
+    pub insert_rate: Option<f64>,
+    /// Seed for everything random, picked at random when not given
+    pub seed: Option<u64>,
+    /// Drive everything by ticks instead of the wall clock
+    pub deterministic: bool,
+    pub hard_limit: HardLimit,
+    /// Appends and pops to do instead of appending until the limit
+    pub script: Option<Vec<(crate::simulation::Operation, usize)>>,
+    /// Size to stop at, instead of when the array fills the grid or hits its limit
+    pub target: Option<usize>,
+    /// How long the final state is held before exiting, in seconds
+    pub hold_seconds: f64,
+    /// Capacity the array starts out with
+    pub initial_capacity: usize,
+    /// Follow the reallocations of a real `Vec<u8>` instead of the growth factor
+    pub vec: bool,
+    /// Capacity of a static array that never grows, overrides the hard limit and initial capacity
+    pub fixed: Option<usize>,
+    pub map_layout: MapLayout,
+    /// Lines of the stat panel in the order they're shown, all of them unless picked with `--metrics`
+    pub metrics: Vec<Metric>,
+    /// Side of a cell in pixels when the window opens, decides how many cells the grid has
+    pub cell_size: usize,
+    /// Shade the live and copied cells by insertion order
+    pub gradient: bool,
+    /// Cells between the grid lines, no lines at all when [`None`]
+    pub grid_every: Option<usize>,
+    /// Plot the capacity vs size graph on a log2 y-axis, `L` toggles it while running
+    pub log_scale: bool,
+    /// Font file to use instead of the embedded one
+    pub font: Option<String>,
+    /// Size of the main font in points, the smaller one is two thirds of it
+    pub font_size: u16,
+    /// Theme to draw with, its palette already swapped for the one picked with `--palette`
+    pub theme: Theme,
+    /// Name of the cell palette
+    pub palette: String,
+    /// Beep on every resize
+    pub sound: bool,
+    /// Wait for vertical sync when presenting
+    pub vsync: bool,
+    /// Frames per second to stay under, uncapped when [`None`]
+    pub max_fps: Option<u32>,
+    /// Sweep growth factors headless instead of showing a run
+    pub benchmark: bool,
+    /// Growth factors to sweep as `from`, `to` and `step`
+    pub sweep: (f64, f64, f64),
+    /// Elements every growth factor of the sweep gets to append
+    pub elements: usize,
+    pub sort_by: SortBy,
+    /// Size of the recording, frames get scaled to it when the window is a different size
+    pub record_width: u32,
+    pub record_height: u32,
+    /// Record only every nth frame
+    pub record_every: u64,
+    /// Frame rate the recording plays at, 60 no matter `--record-every` unless set
+    pub record_fps: u32,
+    /// Codec and quality of the recording
+    #[cfg(feature = "recording")]
+    pub encoder: crate::ffmpeg::Encoder,
+    /// Record the run into a video with FFMpeg
+    pub record: bool,
+    /// Directory to write the recorded frames into as PNGs instead of a video
+    pub png_dir: Option<String>,
+    /// Write the recorded frames into stdout as raw RGB24 instead of a video
+    pub raw_stdout: bool,
+    /// Seconds the title card is shown for before the run starts, none when 0
+    pub intro_seconds: f64,
+    /// Print the version and exit
+    pub show_version: bool,
+    /// The options the run ends up with, a `--config` file's expanded into them, quoted for a shell so the run can be repeated
+    pub effective_options: String,
+    /// Pause on every resize and explain it
+    pub explain: bool,
+    /// How long a pause of `--explain` lasts unless a key ends it, in seconds
+    pub explain_pause: f64,
+    /// Frames a resize sweeps the new slots in over before the run goes on, off when [`None`]
+    pub animate_resize: Option<u64>,
+    /// Leave out the line printed after every append
+    pub quiet: bool,
+    /// Check the model for inconsistencies after every tick and panic on the first one
+    pub check_invariants: bool,
+    /// Elements every append puts in at once, like `extend_from_slice` with a chunk this big
+    pub bulk: Option<usize>,
+    /// Sizes at which room for that many more elements gets reserved, sorted by size
+    pub reserve_at: Vec<(usize, usize)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            growth: 1.618,
+            growth_schedule: vec![],
+            tiers: vec![],
+            growth_label: "1.618".to_string(),
+            growth_preset: None,
+            stats_out: None,
+            svg_out: None,
+            csv: None,
+            replay: None,
+            shrink_threshold: None,
+            compare: vec![],
+            overlay: vec![],
+            copies_per_frame: None,
+            copy_model: CopyModel::Incremental,
+            show_freelist: false,
+            compare_std: false,
+            log_events: None,
+            appends_per_frame: 1,
+            element_size: 1,
+            element_cost: None,
+            inplace_prob: 0.0,
+            insert_rate: None,
+            seed: None,
+            deterministic: false,
+            hard_limit: HardLimit::Grid,
+            script: None,
+            target: None,
+            hold_seconds: crate::simulation::HOLD_SECONDS,
+            initial_capacity: 1,
+            fixed: None,
+            vec: false,
+            map_layout: MapLayout::Grid,
+            metrics: Metric::all(),
+            cell_size: 10,
+            gradient: false,
+            grid_every: Some(1),
+            log_scale: false,
+            font: None,
+            font_size: 30,
+            theme: Theme::LIGHT,
+            palette: "standard".to_string(),
+            sound: false,
+            vsync: false,
+            max_fps: Some(60),
+            benchmark: false,
+            sweep: (1.1, 3.0, 0.1),
+            elements: 1_000_000,
+            sort_by: SortBy::Copies,
+            record_width: 1600,
+            record_height: 1000,
+            record_every: 1,
+            record_fps: crate::simulation::TICKS_PER_SECOND as u32,
+            #[cfg(feature = "recording")]
+            encoder: Default::default(),
+            record: true,
+            intro_seconds: 0.0,
+            show_version: false,
+            effective_options: String::new(),
+            explain: false,
+            explain_pause: 3.0,
+            animate_resize: None,
+            quiet: false,
+            check_invariants: false,
+            bulk: None,
+            reserve_at: vec![],
+            png_dir: None,
+            raw_stdout: false,
+        }
+    }
+}
+
+/// Parses a growth factor, only factors above 1 make the array any bigger when multiplying
+fn growth_factor(value: &str) -> Result<f64, String> {
+    let growth: f64 = value.trim().parse().map_err(|_| format!("Invalid growth factor: {}", value))?;
+    if growth <= 1.0 || !growth.is_finite() {
+        return Err(format!("Growth factor must be bigger than 1.0, got {}", value));
+    }
+    Ok(growth)
+}
+
+/// Takes the value following `flag`
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{} expects a value", flag))
+}
+
+/// Takes the value following `flag` and parses it
+fn parsed<T: FromStr>(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<T, String> {
+    let v = value(args, flag)?;
+    v.parse().map_err(|_| format!("Invalid value for {}: {}", flag, v))
+}
+
+/// Turns the options in a `--config` file into arguments. The file is a JSON object with an entry per option,
+/// named like the flag without the dashes: `{"growth": 2.0, "theme": "dark", "no-record": true, "compare": [1.5, 2.0]}`.
+/// `true` passes a flag that has no value and `false` leaves it out, lists get joined with commas
+fn config_file_args(path: &str) -> Result<Vec<String>, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {} ({})", path, e))?;
+    let options: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(&contents).map_err(|e| format!("{} isn't a JSON object of options ({})", path, e))?;
+    let scalar = |value: &serde_json::Value| match value {
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        serde_json::Value::String(string) => Some(string.clone()),
+        _ => None,
+    };
+    let mut args = vec![];
+    for (name, value) in &options {
+        if name == "config" {
+            return Err(format!("{} can't load another config file", path));
+        }
+        let flag = format!("--{}", name);
+        match value {
+            serde_json::Value::Bool(true) => args.push(flag),
+            serde_json::Value::Bool(false) => {}
+            serde_json::Value::Array(values) => {
+                let values = values.iter().map(scalar).collect::<Option<Vec<_>>>().ok_or_else(|| format!("Invalid value for {} in {}", name, path))?;
+                args.extend([flag, values.join(",")]);
+            }
+            value => args.extend([flag, scalar(value).ok_or_else(|| format!("Invalid value for {} in {}", name, path))?]),
+        }
+    }
+    Ok(args)
+}
+
+/// `arg` as a shell reads it back, in single quotes unless it's only made of characters that need none
+fn shell_quoted(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_.,:/=+@%".contains(c)) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Replaces `--config <path>` with the options in the file. They go in front of everything else,
+/// so the flags on the command line override them no matter where `--config` is
+fn expand_config_file(args: Vec<String>) -> Result<Vec<String>, String> {
+    let Some(position) = args.iter().position(|arg| arg == "--config") else { return Ok(args) };
+    let path = args.get(position + 1).ok_or_else(|| "--config expects a value".to_string())?;
+    let mut expanded = config_file_args(path)?;
+    expanded.extend(args[..position].iter().cloned());
+    expanded.extend(args[position + 2..].iter().cloned());
+    Ok(expanded)
+}
+
+impl Config {
+    /// Stdout is kept for the frames of `--raw-stdout` or the statistics JSON, so everything printed along the way goes to stderr.
+    /// A benchmark or `--version` prints its own output there and has no JSON
+    pub fn logs_to_stderr(&self) -> bool {
+        self.raw_stdout || (self.stats_out.is_none() && !self.benchmark && !self.show_version)
+    }
+
+    /// Reads the options from the process arguments and the `--config` file if there is one.
+    /// The options it ends up with are kept in [`Config::effective_options`] for printing
+    pub fn from_args() -> Result<Self, String> {
+        let args = expand_config_file(std::env::args().skip(1).collect())?;
+        let effective_options = args.iter().map(|arg| shell_quoted(arg)).collect::<Vec<_>>().join(" ");
+        let mut config = Self::parse(args)?;
+        config.effective_options = effective_options;
+        Ok(config)
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--stats-out" => config.stats_out = Some(value(&mut args, &arg)?),
+                "--csv" => config.csv = Some(value(&mut args, &arg)?),
+                "--replay" => config.replay = Some(value(&mut args, &arg)?),
+                "--no-record" => config.record = false,
+                "--hard-limit" => {
+                    let limit: usize = parsed(&mut args, &arg)?;
+                    if limit == 0 {
+                        return Err("--hard-limit must be at least 1".to_string());
+                    }
+                    config.hard_limit = HardLimit::Fixed(limit);
+                }
+                "--no-limit" => config.hard_limit = HardLimit::Unlimited,
+                "--target" => {
+                    let target: usize = parsed(&mut args, &arg)?;
+                    if target == 0 {
+                        return Err("--target must be at least 1".to_string());
+                    }
+                    config.target = Some(target);
+                }
+                "--insert-rate" => {
+                    let rate: f64 = parsed(&mut args, &arg)?;
+                    if !(rate.is_finite() && rate > 0.0) {
+                        return Err("--insert-rate must be bigger than 0".to_string());
+                    }
+                    config.insert_rate = Some(rate);
+                }
+                "--hold-seconds" => {
+                    config.hold_seconds = parsed(&mut args, &arg)?;
+                    if !(config.hold_seconds.is_finite() && config.hold_seconds >= 0.0) {
+                        return Err("--hold-seconds can't be negative".to_string());
+                    }
+                }
+                "--vec" => config.vec = true,
+                "--compare-std" => config.compare_std = true,
+                "--log-events" => config.log_events = Some(value(&mut args, &arg)?),
+                "--fixed" => {
+                    let capacity: usize = parsed(&mut args, &arg)?;
+                    if capacity == 0 {
+                        return Err("--fixed must be at least 1".to_string());
+                    }
+                    config.fixed = Some(capacity);
+                }
+                "--initial-capacity" => config.initial_capacity = parsed(&mut args, &arg)?,
+                "--theme" => {
+                    let name = value(&mut args, &arg)?;
+                    config.theme = Theme::from_name(&name).ok_or_else(|| format!("Unknown theme: {} (expected dark or light)", name))?;
+                }
+                "--layout" => {
+                    config.map_layout = match value(&mut args, &arg)?.as_str() {
+                        "grid" => MapLayout::Grid,
+                        "linear" => MapLayout::Linear,
+                        "history" => MapLayout::History,
+                        other => return Err(format!("Unknown layout: {} (expected grid, linear or history)", other)),
+                    };
+                }
+                "--metrics" => {
+                    config.metrics = value(&mut args, &arg)?
+                        .split(',')
+                        .map(|name| {
+                            let name = name.trim();
+                            Metric::from_name(name).ok_or_else(|| {
+                                let known = Metric::NAMES.iter().map(|&(_, n)| n).collect::<Vec<_>>().join(", ");
+                                format!("Unknown metric: {} (expected some of {})", name, known)
+                            })
+                        })
+                        .collect::<Result<_, _>>()?;
+                }
+                "--font" => config.font = Some(value(&mut args, &arg)?),
+                "--font-size" => {
+                    config.font_size = parsed(&mut args, &arg)?;
+                    if config.font_size == 0 {
+                        return Err("--font-size must be at least 1".to_string());
+                    }
+                }
+                "--gradient" => config.gradient = true,
+                "--log-scale" => config.log_scale = true,
+                "--cell-size" => {
+                    config.cell_size = parsed(&mut args, &arg)?;
+                    if !(1..=500).contains(&config.cell_size) {
+                        return Err("--cell-size must be between 1 and 500".to_string());
+                    }
+                }
+                "--no-grid" => config.grid_every = None,
+                "--grid-every" => {
+                    let every: usize = parsed(&mut args, &arg)?;
+                    if every == 0 {
+                        return Err("--grid-every must be at least 1".to_string());
+                    }
+                    config.grid_every = Some(every);
+                }
+                "--quiet" => config.quiet = true,
+                "--history" => config.map_layout = MapLayout::History,
+                "--show-freelist" => config.show_freelist = true,
+                "--script" => config.script = Some(crate::simulation::parse_script(&value(&mut args, &arg)?)?),
+                "--step-script" => {
+                    let path = value(&mut args, &arg)?;
+                    let script = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {} ({})", path, e))?;
+                    config.script = Some(crate::simulation::parse_script(&script).map_err(|e| format!("{}: {}", path, e))?);
+                }
+                "--png-dir" => {
+                    config.png_dir = Some(value(&mut args, &arg)?);
+                    config.record = false;
+                }
+                "--raw-stdout" => {
+                    config.raw_stdout = true;
+                    config.record = false;
+                }
+                "--version" => config.show_version = true,
+                "--intro-seconds" => {
+                    config.intro_seconds = parsed(&mut args, &arg)?;
+                    if !(config.intro_seconds.is_finite() && config.intro_seconds >= 0.0) {
+                        return Err("--intro-seconds can't be negative".to_string());
+                    }
+                }
+                "--animate-resize" => {
+                    config.animate_resize.get_or_insert(ANIMATE_FRAMES);
+                }
+                "--animate-frames" => {
+                    let frames = parsed(&mut args, &arg)?;
+                    if frames == 0 {
+                        return Err("--animate-frames has to be at least 1".to_string());
+                    }
+                    config.animate_resize = Some(frames);
+                }
+                "--check-invariants" => config.check_invariants = true,
+                "--bulk" => {
+                    let bulk = parsed(&mut args, &arg)?;
+                    if bulk == 0 {
+                        return Err("--bulk must be at least 1".to_string());
+                    }
+                    config.bulk = Some(bulk);
+                }
+                "--explain" => config.explain = true,
+                "--explain-pause" => {
+                    config.explain_pause = parsed(&mut args, &arg)?;
+                    if !(config.explain_pause.is_finite() && config.explain_pause >= 0.0) {
+                        return Err("--explain-pause can't be negative".to_string());
+                    }
+                    config.explain = true;
+                }
+                "--svg-out" => config.svg_out = Some(value(&mut args, &arg)?),
+                "--palette" => config.palette = value(&mut args, &arg)?,
+                "--benchmark" => config.benchmark = true,
+                "--sweep" => {
+                    let sweep = value(&mut args, &arg)?;
+                    let parts = sweep
+                        .split(':')
+                        .map(|p| p.trim().parse::<f64>().map_err(|_| format!("Invalid value for --sweep: {}", sweep)))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let [from, to, step] = parts[..] else {
+                        return Err("--sweep expects from:to:step, like --sweep 1.1:3.0:0.1".to_string());
+                    };
+                    if from <= 1.0 || to < from || step <= 0.0 {
+                        return Err("--sweep needs 1 < from <= to and a positive step".to_string());
+                    }
+                    config.sweep = (from, to, step);
+                }
+                "--elements" => config.elements = parsed(&mut args, &arg)?,
+                "--sort-by" => {
+                    let name = value(&mut args, &arg)?;
+                    config.sort_by = SortBy::from_name(&name).ok_or_else(|| format!("Unknown metric: {} (expected resizes, copies or efficiency)", name))?;
+                }
+                "--record-width" | "--record-height" => {
+                    let size: u32 = parsed(&mut args, &arg)?;
+                    // yuv420p needs both to be even
+                    if size == 0 || !size.is_multiple_of(2) {
+                        return Err(format!("{} must be even and at least 2", arg));
+                    }
+                    if arg == "--record-width" {
+                        config.record_width = size;
+                    } else {
+                        config.record_height = size;
+                    }
+                }
+                "--sound" => config.sound = true,
+                "--record-every" => {
+                    config.record_every = parsed(&mut args, &arg)?;
+                    if config.record_every == 0 {
+                        return Err("--record-every must be at least 1".to_string());
+                    }
+                }
+                "--record-fps" => {
+                    config.record_fps = parsed(&mut args, &arg)?;
+                    if config.record_fps == 0 {
+                        return Err("--record-fps must be at least 1".to_string());
+                    }
+                }
+                #[cfg(not(feature = "recording"))]
+                "--codec" | "--crf" => return Err(format!("{} needs a build with the recording feature", arg)),
+                #[cfg(feature = "recording")]
+                "--codec" => {
+                    let codec = value(&mut args, &arg)?;
+                    config.encoder.codec = match codec.as_str() {
+                        "x264" | "h264" => "libx264".to_string(),
+                        "x265" | "h265" | "hevc" => "libx265".to_string(),
+                        "vp9" => "libvpx-vp9".to_string(),
+                        _ if !codec.is_empty() && codec.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') => codec,
+                        _ => return Err(format!("Invalid codec name: {}", codec)),
+                    };
+                }
+                #[cfg(feature = "recording")]
+                "--crf" => {
+                    let crf: u32 = parsed(&mut args, &arg)?;
+                    if crf > 63 {
+                        return Err("--crf must be between 0 and 63".to_string());
+                    }
+                    config.encoder.crf = Some(crf);
+                }
+                "--vsync" => config.vsync = true,
+                "--max-fps" => {
+                    let max_fps: u32 = parsed(&mut args, &arg)?;
+                    config.max_fps = if max_fps == 0 { None } else { Some(max_fps) };
+                }
+                "--deterministic" => config.deterministic = true,
+                "--inplace-prob" => {
+                    config.inplace_prob = parsed(&mut args, &arg)?;
+                    if !(0.0..=1.0).contains(&config.inplace_prob) {
+                        return Err("--inplace-prob must be between 0.0 and 1.0".to_string());
+                    }
+                }
+                "--seed" => config.seed = Some(parsed(&mut args, &arg)?),
+                "--element-size" => {
+                    let size = parsed(&mut args, &arg)?;
+                    if size == 0 {
+                        return Err("--element-size must be at least 1".to_string());
+                    }
+                    config.element_size = size;
+                }
+                "--element-cost" => {
+                    let cost: f64 = parsed(&mut args, &arg)?;
+                    if !(cost >= 0.0 && cost.is_finite()) {
+                        return Err("--element-cost must be a number of nanoseconds, 0 or more".to_string());
+                    }
+                    config.element_cost = Some(cost);
+                }
+                "--copies-per-frame" => {
+                    let copies: usize = parsed(&mut args, &arg)?;
+                    if copies == 0 {
+                        return Err("--copies-per-frame must be at least 1".to_string());
+                    }
+                    config.copies_per_frame = Some(copies);
+                }
+                "--copy-model" => {
+                    let name = value(&mut args, &arg)?;
+                    config.copy_model = CopyModel::from_name(&name).ok_or_else(|| format!("Unknown copy model: {} (expected instant or incremental)", name))?;
+                }
+                "--appends-per-frame" => {
+                    config.appends_per_frame = parsed(&mut args, &arg)?;
+                    if config.appends_per_frame == 0 {
+                        return Err("--appends-per-frame must be at least 1".to_string());
+                    }
+                }
+                "--compare" => {
+                    let growths = value(&mut args, &arg)?;
+                    config.compare = growths
+                        .split(',')
+                        .map(growth_factor)
+                        .collect::<Result<_, _>>()?;
+                    if config.compare.len() != 2 {
+                        return Err("--compare expects exactly two growth factors, like --compare 1.5,2.0".to_string());
+                    }
+                    config.growth_label = growths.replace(',', "_vs_");
+                }
+                "--overlay" => {
+                    let growths = value(&mut args, &arg)?;
+                    config.overlay = growths
+                        .split(',')
+                        .map(growth_factor)
+                        .collect::<Result<_, _>>()?;
+                    if !(2..=MAX_OVERLAY).contains(&config.overlay.len()) {
+                        return Err(format!("--overlay expects 2 to {} growth factors, like --overlay 1.5,1.618,2.0", MAX_OVERLAY));
+                    }
+                    config.growth = config.overlay[0];
+                    config.growth_label = growths.replace(',', "_vs_");
+                }
+                "--shrink-threshold" => {
+                    let threshold: f64 = parsed(&mut args, &arg)?;
+                    if threshold <= 1.0 {
+                        return Err("--shrink-threshold must be bigger than 1.0".to_string());
+                    }
+                    config.shrink_threshold = Some(threshold);
+                }
+                "--growth" => {
+                    let growth = value(&mut args, &arg)?;
+                    config.growth = growth_factor(&growth)?;
+                    config.growth_label = growth;
+                    config.growth_preset = None;
+                }
+                "--growth-preset" => {
+                    let name = value(&mut args, &arg)?;
+                    let &(preset, growth) = GROWTH_PRESETS.iter().find(|(preset, _)| *preset == name).ok_or_else(|| {
+                        let known = GROWTH_PRESETS.iter().map(|(preset, growth)| format!("{} ({})", preset, growth)).collect::<Vec<_>>().join(", ");
+                        format!("Unknown growth preset: {} (expected one of {})", name, known)
+                    })?;
+                    config.growth = growth;
+                    config.growth_label = growth.to_string();
+                    config.growth_preset = Some(preset);
+                }
+                "--growth-schedule" => {
+                    let schedule = value(&mut args, &arg)?;
+                    config.growth_schedule = schedule
+                        .split(',')
+                        .map(|part| {
+                            let (size, growth) = part.split_once(':').ok_or_else(|| format!("--growth-schedule expects size:factor pairs, like --growth-schedule 0:2.0,10000:1.5, got {}", part))?;
+                            let size = size.trim().parse::<usize>().map_err(|_| format!("Invalid size in --growth-schedule: {}", size))?;
+                            Ok((size, growth_factor(growth)?))
+                        })
+                        .collect::<Result<_, String>>()?;
+                    config.growth_schedule.sort_by_key(|&(size, _)| size);
+                }
+                "--reserve-at" => {
+                    let reserves = value(&mut args, &arg)?;
+                    config.reserve_at = reserves
+                        .split(',')
+                        .map(|part| {
+                            let (size, amount) = part.split_once(':').ok_or_else(|| format!("--reserve-at expects size:amount pairs, like --reserve-at 100:5000, got {}", part))?;
+                            let size = size.trim().parse::<usize>().map_err(|_| format!("Invalid size in --reserve-at: {}", size))?;
+                            let amount = amount.trim().parse::<usize>().map_err(|_| format!("Invalid amount in --reserve-at: {}", amount))?;
+                            Ok((size, amount))
+                        })
+                        .collect::<Result<_, String>>()?;
+                    config.reserve_at.sort_by_key(|&(size, _)| size);
+                }
+                "--tiers" => {
+                    let tiers = value(&mut args, &arg)?;
+                    config.tiers = tiers
+                        .split(',')
+                        .map(|tier| tier.trim().parse::<usize>().ok().filter(|&tier| tier > 0).ok_or_else(|| format!("Invalid tier in --tiers: {}", tier)))
+                        .collect::<Result<_, _>>()?;
+                }
+                flag if flag.starts_with("--") => return Err(format!("Unknown option: {}", flag)),
+                growth => {
+                    config.growth = growth_factor(growth)?;
+                    config.growth_label = growth.to_string();
+                    config.growth_preset = None;
+                }
+            }
+        }
+        if config.vec && (config.fixed.is_some() || config.shrink_threshold.is_some()) {
+            return Err("--vec can't be used with --fixed or --shrink-threshold, a Vec never shrinks or stops growing on its own".to_string());
+        }
+        if config.copy_model == CopyModel::Instant && config.copies_per_frame.is_some() {
+            return Err("--copies-per-frame can't be used with --copy-model instant, there's nothing left to copy after a resize".to_string());
+        }
+        if !config.compare.is_empty() && !config.overlay.is_empty() {
+            return Err("--compare and --overlay can't be used together".to_string());
+        }
+        if config.compare_std && (config.vec || !config.compare.is_empty()) {
+            return Err("--compare-std can't be used with --vec or --compare, it compares a single model against std::Vec".to_string());
+        }
+        if config.vec && !config.tiers.is_empty() {
+            return Err("--tiers can't be used with --vec, a Vec picks its own capacities".to_string());
+        }
+        if !config.growth_schedule.is_empty() && (config.vec || !config.compare.is_empty() || !config.overlay.is_empty()) {
+            return Err("--growth-schedule can't be used with --vec, --compare or --overlay, it would override their growth factors".to_string());
+        }
+        if config.vec && !config.reserve_at.is_empty() {
+            return Err("--reserve-at can't be used with --vec, a Vec picks its own capacities".to_string());
+        }
+        if config.bulk.is_some() && config.script.is_some() {
+            return Err("--bulk can't be used with --script or --step-script, the script counts single appends".to_string());
+        }
+        config.theme.palette = Palette::from_name(&config.palette)
+            .ok_or_else(|| format!("Unknown palette: {} (expected standard or colorblind)", config.palette))?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_files_come_before_the_command_line() {
+        let path = std::env::temp_dir().join(format!("config-test-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"growth": 2.0, "theme": "dark", "no-record": true, "vsync": false, "compare": [1.5, 2]}"#).unwrap();
+        let args = ["--theme", "light", "--config", path.to_str().unwrap()].map(String::from).to_vec();
+        let expanded = expand_config_file(args).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // the file's options come out sorted by name
+        assert_eq!(expanded, ["--compare", "1.5,2", "--growth", "2.0", "--no-record", "--theme", "dark", "--theme", "light"]);
+        let config = Config::parse(expanded).unwrap();
+        assert_eq!(config.theme, Theme::LIGHT);
+        assert_eq!(config.compare, vec![1.5, 2.0]);
+        assert!(!config.record);
+    }
+
+    #[test]
+    fn stdout_is_left_to_the_json() {
+        let logs_to_stderr = |args: &[&str]| Config::parse(args.iter().map(|arg| arg.to_string())).unwrap().logs_to_stderr();
+        assert!(logs_to_stderr(&[]));
+        assert!(!logs_to_stderr(&["--stats-out", "stats.json"]));
+        assert!(logs_to_stderr(&["--raw-stdout", "--stats-out", "stats.json"]));
+        assert!(!logs_to_stderr(&["--benchmark"]));
+    }
+
+    #[test]
+    fn options_get_quoted_for_a_shell() {
+        assert_eq!(shell_quoted("--growth"), "--growth");
+        assert_eq!(shell_quoted("1.5,2"), "1.5,2");
+        assert_eq!(shell_quoted("size, capacity"), "'size, capacity'");
+        assert_eq!(shell_quoted("it's"), r"'it'\''s'");
+        assert_eq!(shell_quoted(""), "''");
+    }
+
+    #[test]
+    fn presets_name_growth_factors() {
+        let config = Config::parse(["--growth-preset", "msvc"].map(String::from).to_vec()).unwrap();
+        assert_eq!((config.growth, config.growth_label.as_str(), config.growth_preset), (1.5, "1.5", Some("msvc")));
+        // whichever comes last wins
+        let config = Config::parse(["--growth-preset", "golden", "--growth", "1.7"].map(String::from).to_vec()).unwrap();
+        assert_eq!((config.growth, config.growth_preset), (1.7, None));
+        let error = Config::parse(["--growth-preset", "java"].map(String::from).to_vec()).unwrap_err();
+        assert_eq!(error, "Unknown growth preset: java (expected one of golden (1.618), double (2), msvc (1.5), folly (1.5))");
+    }
+
+    #[test]
+    fn metrics_keep_their_order() {
+        let config = Config::parse(["--metrics", "size, capacity,efficiency"].map(String::from).to_vec()).unwrap();
+        assert_eq!(config.metrics, vec![Metric::Size, Metric::Capacity, Metric::Efficiency]);
+        assert_eq!(Config::parse(vec![]).unwrap().metrics.len(), Metric::NAMES.len());
+        let error = Config::parse(["--metrics", "size,speed"].map(String::from).to_vec()).unwrap_err();
+        assert!(error.starts_with("Unknown metric: speed (expected some of efficiency, ops,"), "{}", error);
+    }
+}
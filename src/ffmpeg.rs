@@ -4,12 +4,24 @@ use std::{
     io::{BufRead, Write},
 };
 /// [`VideoRecorder`] struct for wrapping around FFMpeg for rendering video by passing frames in [`Vec<u8>`]
+/// A disabled recorder (see [`VideoRecorder::disabled`]) has no FFMpeg behind it and just ignores everything
 pub struct VideoRecorder {
-    ffmpeg: std::process::Child,
+    ffmpeg: Option<std::process::Child>,
+    /// Frames waiting for [`VideoRecorder::writer`], at most [`FRAME_QUEUE`] of them
+    frames: Option<std::sync::mpsc::SyncSender<Vec<u8>>>,
+    /// Thread writing the frames into FFMpeg's stdin, so a slow encoder doesn't hold up drawing
+    writer: Option<std::thread::JoinHandle<std::io::Result<()>>>,
     status_receiver: std::sync::mpsc::Receiver<String>,
     frame_count: u128,
+    /// Everything FFMpeg wrote to stderr, which is only errors since it runs with `-loglevel error`
+    errors: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    /// Thread collecting [`VideoRecorder::errors`], done once FFMpeg exits
+    error_reader: Option<std::thread::JoinHandle<()>>,
 }
 
+/// Frames that can be waiting for FFMpeg before [`VideoRecorder::process_frame`] blocks until it catches up
+const FRAME_QUEUE: usize = 8;
+
 /// Storing FFMpeg informations on current rendering.  
 /// frame= 4852 fps=7.0 q=-1.0 Lsize=   37966kB time=00:01:20.81 bitrate=3848.4kbits/s speed=0.117x  
 /// Going for biggest data type I can do
@@ -68,12 +80,61 @@ fn parsery(time: &str) -> std::time::Duration {
     )
 }
 
+/// Video codec and quality handed to FFMpeg
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Encoder {
+    /// FFMpeg encoder name, like `libx264`
+    pub codec: String,
+    /// Constant rate factor, lower is better quality. FFMpeg's default for the codec when [`None`]
+    pub crf: Option<u32>,
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self {
+            codec: "libx264".to_string(),
+            crf: None,
+        }
+    }
+}
+
+impl Encoder {
+    /// Extra arguments the codec needs on top of `-c:v`
+    fn args(&self) -> Vec<String> {
+        let mut args = vec![];
+        // only the x264 family has presets
+        if self.codec == "libx264" || self.codec == "libx265" {
+            args.extend(["-preset".to_string(), "veryslow".to_string()]);
+        }
+        if let Some(crf) = self.crf {
+            args.extend(["-crf".to_string(), crf.to_string()]);
+            // otherwise VP9 treats the CRF as a quality cap for its default bitrate
+            if self.codec == "libvpx-vp9" {
+                args.extend(["-b:v".to_string(), "0".to_string()]);
+            }
+        }
+        args
+    }
+}
+
+/// Whether the installed FFMpeg has an encoder called `codec`
+pub fn encoder_available(codec: &str) -> bool {
+    let Ok(output) = std::process::Command::new("ffmpeg").args(["-hide_banner", "-encoders"]).output() else {
+        return false;
+    };
+    String::from_utf8_lossy(&output.stdout).lines().any(|line| line.split_whitespace().nth(1) == Some(codec))
+}
+
 impl VideoRecorder {
-    /// Spawns new instance of FFMpeg with out file, size and FPS
-    pub fn new(out: &str, width: u32, height: u32, fps: u32) -> Self {
-        let mut ffmpeg_cmd = std::process::Command::new("ffmpeg")
+    /// Spawns new instance of FFMpeg with out file, size, FPS and encoder
+    /// Will return error if FFMpeg couldn't be started (most likely it's not installed)
+    pub fn new(out: &str, width: u32, height: u32, fps: u32, encoder: &Encoder) -> std::io::Result<Self> {
+        let mut command = std::process::Command::new("ffmpeg");
+        command
             .args([
                 "-hide_banner",
+                "-loglevel",
+                "error",
                 "-f",
                 "rawvideo",
                 "-pix_fmt",
@@ -85,20 +146,37 @@ impl VideoRecorder {
                 "-i",
                 "pipe:0",
                 "-c:v",
-                "libx264",
+                &encoder.codec,
                 "-pix_fmt",
                 "yuv420p",
-                "-preset",
-                "veryslow",
-                "-y",
-                "-progress",
-                "pipe:1",
-                out,
             ])
+            .args(encoder.args())
+            .args(["-y", "-progress", "pipe:1", out])
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
-            .spawn()
-            .expect("FFMpeg failed to start");
+            .stderr(std::process::Stdio::piped());
+        // keep Ctrl-C in the terminal from reaching FFMpeg, we finish the video ourselves
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+            command.creation_flags(CREATE_NEW_PROCESS_GROUP);
+        }
+        let mut ffmpeg_cmd = command.spawn()?;
+        let mut stdin = ffmpeg_cmd.stdin.take().expect("Failed to take STDIN");
+        let (frames, queue) = std::sync::mpsc::sync_channel::<Vec<u8>>(FRAME_QUEUE);
+        // ends once the recorder hangs up or FFMpeg stops taking frames, dropping stdin tells FFMpeg the video is done
+        let writer = std::thread::spawn(move || {
+            for frame in queue {
+                stdin.write_all(&frame)?;
+            }
+            Ok(())
+        });
         let (tx, rx) = std::sync::mpsc::channel();
         let output_lines = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
         let ol_cloned = std::sync::Arc::clone(&output_lines);
@@ -113,26 +191,86 @@ impl VideoRecorder {
                     .expect("Failed to send FFMpeg line to main thread")
             }
         });
+        let errors = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let errors_cloned = std::sync::Arc::clone(&errors);
+        let stderr = std::io::BufReader::new(ffmpeg_cmd.stderr.take().expect("Failed to take STDERR"));
+        let error_reader = std::thread::spawn(move || {
+            for line in stderr.lines().map_while(Result::ok) {
+                errors_cloned.lock().unwrap().push(line);
+            }
+        });
+        Ok(Self {
+            ffmpeg: Some(ffmpeg_cmd),
+            frames: Some(frames),
+            writer: Some(writer),
+            status_receiver: rx,
+            frame_count: 0,
+            errors,
+            error_reader: Some(error_reader),
+        })
+    }
+
+    /// Recorder that doesn't record anything, for running the visualization only
+    pub fn disabled() -> Self {
+        let (_, rx) = std::sync::mpsc::channel();
         Self {
-            ffmpeg: ffmpeg_cmd,
+            ffmpeg: None,
+            frames: None,
+            writer: None,
             status_receiver: rx,
             frame_count: 0,
+            errors: Default::default(),
+            error_reader: None,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.ffmpeg.is_some()
+    }
+
+    /// What FFMpeg complained about, for putting into error messages. Only call once FFMpeg exited
+    fn error_output(&mut self) -> String {
+        if let Some(reader) = self.error_reader.take() {
+            let _ = reader.join();
+        }
+        let errors = self.errors.lock().unwrap();
+        if errors.is_empty() {
+            "no output from FFMpeg".to_string()
+        } else {
+            errors.join("\n")
         }
     }
 
-    /// Function for passing the frames to FFMpeg. This doesn't cost a lot performance.
-    pub fn process_frame(&mut self, frame: Vec<u8>) {
-        self.ffmpeg
-            .stdin
-            .as_mut()
-            .unwrap()
-            .write_all(frame.as_slice())
-            .expect("Pipe is closed.");
+    /// Hands the frame over to the thread writing into FFMpeg, only waiting when it's [`FRAME_QUEUE`] frames behind.
+    /// When FFMpeg is gone (crashed, disk full, ...) recording stops and the error says what FFMpeg printed,
+    /// every frame after that gets ignored like with a disabled recorder
+    pub fn process_frame(&mut self, frame: Vec<u8>) -> std::io::Result<()> {
+        let Some(frames) = self.frames.as_ref() else {
+            return Ok(());
+        };
+        if frames.send(frame).is_err() {
+            // the writer only hangs up when writing failed
+            self.frames = None;
+            let e = self.join_writer().err().unwrap_or_else(|| std::io::Error::from(std::io::ErrorKind::BrokenPipe));
+            self.kill();
+            return Err(std::io::Error::new(e.kind(), format!("{} ({})", e, self.error_output())));
+        }
         self.frame_count += 1;
+        Ok(())
+    }
+
+    /// Waits for the writer thread to write what's left and returns how that went
+    fn join_writer(&mut self) -> std::io::Result<()> {
+        match self.writer.take().map(std::thread::JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(std::io::Error::other("the thread writing frames panicked")),
+            None => Ok(()),
+        }
     }
 
     pub fn get_render_status(&mut self) -> Option<FFMpegStatus> {
-        if let Ok(Some(_)) = self.ffmpeg.try_wait() {
+        let ffmpeg = self.ffmpeg.as_mut()?;
+        if let Ok(Some(_)) = ffmpeg.try_wait() {
             return Some(FFMpegStatus {
                 done: true,
                 ..Default::default()
@@ -174,17 +312,25 @@ impl VideoRecorder {
         Some(st)
     }
 
-    /// Finalizing rendering. Wait for FFMpeg to exit
-    pub fn done(&mut self) {
-        if let Some(stdin) = self.ffmpeg.stdin.take() {
-            drop(stdin)
+    fn still_running(&mut self) -> bool {
+        matches!(self.ffmpeg.as_mut().map(|f| f.try_wait()), Some(Ok(None)))
+    }
+
+    /// Finalizing rendering. Waits for the queued frames to be written and FFMpeg to exit,
+    /// failing when either didn't go cleanly
+    pub fn done(&mut self) -> std::io::Result<()> {
+        if self.ffmpeg.is_none() {
+            return Ok(());
         }
-        if let Ok(Some(_)) = self.ffmpeg.try_wait() {
-            println!("FFMpeg already exited");
+        // hanging up lets the writer finish the queue and close stdin
+        self.frames = None;
+        let written = self.join_writer();
+        if !self.still_running() {
+            info!("FFMpeg already exited");
         } else {
-            while let Ok(None) = self.ffmpeg.try_wait() {
+            while self.still_running() {
                 std::thread::sleep(std::time::Duration::from_millis(100));
-                println!(
+                progress!(
                     "Waiting for FFMpeg to exit... (Progress: {}%)",
                     self.get_render_status()
                         .unwrap_or_default()
@@ -193,9 +339,28 @@ impl VideoRecorder {
                 );
             }
         }
+        let status = self.ffmpeg.take().unwrap().wait()?;
+        if !status.success() {
+            return Err(std::io::Error::other(format!("FFMpeg exited with {} ({})", status, self.error_output())));
+        }
+        written
     }
 
+    /// Stops FFMpeg right away without finishing the video, nothing happens when it isn't running
     pub fn kill(&mut self) {
-        self.ffmpeg.kill().unwrap();
+        if let Some(mut ffmpeg) = self.ffmpeg.take() {
+            let _ = ffmpeg.kill();
+            let _ = ffmpeg.wait();
+        }
+    }
+}
+
+/// Finishes a recording that never got [`VideoRecorder::done`], like when the loop panicked,
+/// so the video up to there is still playable
+impl Drop for VideoRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.done() {
+            warn!("The recording may be broken: {}", e);
+        }
     }
-}
\ No newline at end of file
+}
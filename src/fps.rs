@@ -0,0 +1,81 @@
+/// Frame rate readout for debug builds
+use std::time::{Duration, Instant};
+
+/// How often the frame rate gets measured
+const SAMPLE_EVERY: Duration = Duration::from_secs(1);
+/// How long the minimum and maximum are taken over before starting over
+const WINDOW: Duration = Duration::from_secs(3);
+
+pub struct FpsCounter {
+    /// Frame rate over the last second
+    pub current: f64,
+    /// Lowest and highest frame rate of the last full window
+    pub min: f64,
+    pub max: f64,
+    frames: u32,
+    sample_start: Instant,
+    window_min: f64,
+    window_max: f64,
+    window_start: Instant,
+}
+
+impl FpsCounter {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            current: 0.0,
+            min: 0.0,
+            max: 0.0,
+            frames: 0,
+            sample_start: now,
+            window_min: f64::INFINITY,
+            window_max: 0.0,
+            window_start: now,
+        }
+    }
+
+    /// Counts a frame presented right now
+    pub fn frame(&mut self) {
+        self.frame_at(Instant::now());
+    }
+
+    fn frame_at(&mut self, now: Instant) {
+        self.frames += 1;
+        let elapsed = now - self.sample_start;
+        if elapsed >= SAMPLE_EVERY {
+            self.current = self.frames as f64 / elapsed.as_secs_f64();
+            self.frames = 0;
+            self.sample_start = now;
+            self.window_min = self.window_min.min(self.current);
+            self.window_max = self.window_max.max(self.current);
+        }
+        if now - self.window_start >= WINDOW {
+            self.min = self.window_min;
+            self.max = self.window_max;
+            self.window_min = f64::INFINITY;
+            self.window_max = 0.0;
+            self.window_start = now;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn min_and_max_cover_the_window() {
+        let mut fps = FpsCounter::new();
+        let mut now = fps.sample_start;
+        // a second at 25 fps, then two at 50
+        for (frames, step) in [(25, 40), (100, 20)] {
+            for _ in 0..frames {
+                now += Duration::from_millis(step);
+                fps.frame_at(now);
+            }
+        }
+        assert_eq!(fps.current, 50.0);
+        assert_eq!(fps.min, 25.0);
+        assert_eq!(fps.max, 50.0);
+    }
+}
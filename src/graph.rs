@@ -0,0 +1,84 @@
+/// Small plotting helpers for drawing data series into a region of the canvas
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+/// Maps a value in `min..=max` to a y pixel inside `area` (bigger values are higher up)
+fn value_to_y(area: Rect, value: f64, min: f64, max: f64) -> i32 {
+    let range = if max > min { max - min } else { 1.0 };
+    let t = ((value - min) / range).clamp(0.0, 1.0);
+    area.bottom() - 1 - (t * (area.height() - 1) as f64).round() as i32
+}
+
+/// `log2` of every sample for plotting on a log scale, anything below 1 (like a starting capacity of 0) counts as 1
+pub fn log2_samples(samples: &[f64]) -> Vec<f64> {
+    samples.iter().map(|s| s.max(1.0).log2()).collect()
+}
+
+/// Draws `samples` as a line graph inside `area` with the y-axis spanning `min..=max`.
+/// When there are more samples than pixel columns, every column gets the min and max of its bucket
+/// so short spikes and troughs don't get lost in the downsampling.
+pub fn draw_line_graph(canvas: &mut Canvas<Window>, area: Rect, samples: &[f64], min: f64, max: f64, color: Color) -> Result<(), String> {
+    if samples.is_empty() {
+        return Ok(());
+    }
+    canvas.set_draw_color(color);
+    let width = area.width() as usize;
+    let mut points = vec![];
+    if samples.len() <= width {
+        let step = (width - 1) as f64 / (samples.len() - 1).max(1) as f64;
+        for (i, &s) in samples.iter().enumerate() {
+            points.push(Point::new(area.x() + (i as f64 * step).round() as i32, value_to_y(area, s, min, max)));
+        }
+    } else {
+        for column in 0..width {
+            let start = column * samples.len() / width;
+            let end = ((column + 1) * samples.len() / width).max(start + 1);
+            let bucket = &samples[start..end];
+            let lo = bucket.iter().cloned().fold(f64::INFINITY, f64::min);
+            let hi = bucket.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let x = area.x() + column as i32;
+            points.push(Point::new(x, value_to_y(area, lo, min, max)));
+            points.push(Point::new(x, value_to_y(area, hi, min, max)));
+        }
+    }
+    if points.len() == 1 {
+        canvas.draw_point(points[0])
+    } else {
+        canvas.draw_lines(points.as_slice())
+    }
+}
+
+/// Draws a horizontal line across `area` at `value` on a y-axis spanning `min..=max`, nothing when it's off the axis
+pub fn draw_marker(canvas: &mut Canvas<Window>, area: Rect, value: f64, min: f64, max: f64, color: Color) -> Result<(), String> {
+    if value < min || value > max {
+        return Ok(());
+    }
+    canvas.set_draw_color(color);
+    let y = value_to_y(area, value, min, max);
+    canvas.draw_line((area.x(), y), (area.right() - 1, y))
+}
+
+/// Draws one bar per bucket for every series side by side, the tallest bar filling the height of `area`
+pub fn draw_bar_chart(canvas: &mut Canvas<Window>, area: Rect, series: &[(Vec<usize>, Color)]) -> Result<(), String> {
+    let buckets = series.iter().map(|(counts, _)| counts.len()).max().unwrap_or(0);
+    let highest = series.iter().flat_map(|(counts, _)| counts.iter().copied()).max().unwrap_or(0);
+    if buckets == 0 || highest == 0 {
+        return Ok(());
+    }
+    let slot = area.width() as f64 / buckets as f64;
+    let bar = (slot / series.len() as f64 - 1.0).max(1.0);
+    for (i, (counts, color)) in series.iter().enumerate() {
+        canvas.set_draw_color(*color);
+        for (bucket, &count) in counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let height = ((count as f64 / highest as f64) * area.height() as f64).round().max(1.0) as u32;
+            let x = area.x() + (bucket as f64 * slot + i as f64 * (bar + 1.0)).round() as i32;
+            canvas.fill_rect(Rect::new(x, area.bottom() - height as i32, bar as u32, height))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,5 @@
+//! The dynamic array model and everything that works on it without a window,
+//! the visualizer binary is built on top of this
+pub mod array;
+pub mod benchmark;
+pub mod stats;
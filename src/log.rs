@@ -0,0 +1,87 @@
+/// Terminal output. Progress lines overwrite each other with `\r` in a terminal,
+/// everything else makes sure it starts on a fresh line after them
+use std::fmt::Arguments;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+/// Set by `--quiet`, drops the progress lines
+static QUIET: AtomicBool = AtomicBool::new(false);
+/// A progress line was printed without a newline after it
+static PENDING: AtomicBool = AtomicBool::new(false);
+/// Set when stdout gets the frames of `--raw-stdout` or the statistics JSON, everything else goes to stderr then
+static STDERR: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Writes everything into stderr instead of stdout from now on
+pub fn use_stderr() {
+    STDERR.store(true, Ordering::Relaxed);
+}
+
+/// Where the progress and info lines go
+fn out() -> Box<dyn Write> {
+    if STDERR.load(Ordering::Relaxed) {
+        Box::new(std::io::stderr().lock())
+    } else {
+        Box::new(std::io::stdout().lock())
+    }
+}
+
+/// Written to a terminal and not piped into a file, where `\r` would just be garbage
+fn terminal() -> bool {
+    static TERMINAL: OnceLock<bool> = OnceLock::new();
+    *TERMINAL.get_or_init(|| if STDERR.load(Ordering::Relaxed) { std::io::stderr().is_terminal() } else { std::io::stdout().is_terminal() })
+}
+
+/// Ends the pending progress line so the next message doesn't get written over it
+fn end_progress() {
+    if PENDING.swap(false, Ordering::Relaxed) {
+        let _ = writeln!(out());
+    }
+}
+
+/// A line that gets replaced by the next one in a terminal, like the size after every append
+pub fn progress(message: Arguments) {
+    if QUIET.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut out = out();
+    if terminal() {
+        let _ = write!(out, "\r{}", message);
+        let _ = out.flush();
+        PENDING.store(true, Ordering::Relaxed);
+    } else {
+        let _ = writeln!(out, "{}", message);
+    }
+}
+
+pub fn info(message: Arguments) {
+    end_progress();
+    let _ = writeln!(out(), "{}", message);
+}
+
+pub fn warn(message: Arguments) {
+    end_progress();
+    eprintln!("{}", message);
+}
+
+macro_rules! progress {
+    ($($arg:tt)*) => {
+        $crate::log::progress(format_args!($($arg)*))
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::log::info(format_args!($($arg)*))
+    };
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        $crate::log::warn(format_args!($($arg)*))
+    };
+}
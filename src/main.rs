@@ -1,10 +1,13 @@
-use std::sync::{Arc, Mutex};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::rwops::RWops;
-
-mod ffmpeg;
+use sdl2::video::FullscreenType;
 
 macro_rules! debuggery {
     ($($e:expr),+) => {
@@ -17,289 +20,787 @@ macro_rules! debuggery {
     };
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-struct Array {
-    growth: f64,
-    old_data_size: usize,
-    size: usize,
-    capacity: usize,
-    hard_limit: Option<usize>,
-    old_data_appended: usize, // Track how much old data has been appended back,
-    resizes: usize,
-    copy_operations: usize
-}
+#[macro_use]
+mod log;
 
-impl Array {
-    fn new(growth: f64, hard_limit: Option<usize>) -> Array {
-        Array {
-            growth,
-            old_data_size: 0,
-            size: 0,
-            capacity: 1,
-            hard_limit,
-            old_data_appended: 0,
-            copy_operations: 0,
-            resizes: 0,
-        }
-    }
+mod config;
+#[cfg(feature = "recording")]
+mod ffmpeg;
+#[cfg(debug_assertions)]
+mod fps;
+mod graph;
+mod metrics;
+mod render;
+mod screenshot;
+mod sound;
+mod simulation;
+mod svg;
+mod theme;
 
-    /// Will return error if capacity is not enough to hold the new data
-    /// Will return [`Ok(usize)`] if the data was added successfully and usize is the address of the new data
-    fn grow(&mut self) -> Result<usize, ()> {
-        let new_size = self.size + 1;
-        if new_size > self.capacity {
-            return Err(());
-        }
-        self.size = new_size;
-        Ok(self.size)
+use growth_size_array::{array, benchmark, stats};
+
+use array::Array;
+use simulation::Simulation;
+
+/// Size of the window when it opens
+const WINDOW_WIDTH: u32 = 1600;
+const WINDOW_HEIGHT: u32 = 1000;
+/// Side of the square the grid gets in the window when it opens
+const GRID_PIXELS: u32 = WINDOW_HEIGHT;
+
+/// How much `[` and `]` change the growth factors by
+const GROWTH_STEP: f64 = 0.1;
+/// How long a toast stays on screen
+const TOAST_DURATION: std::time::Duration = std::time::Duration::from_millis(1500);
+
+/// Most bars the resize size histogram gets, capacities past the last one are counted into it
+const HISTOGRAM_BUCKETS: usize = 16;
+
+/// Colors of each array's line in the efficiency graph
+const SERIES_COLORS: [Color; 2] = [Color::GREEN, Color::MAGENTA];
+/// Colors of the extra growth factors of `--overlay` in the efficiency graph
+const OVERLAY_COLORS: [Color; config::MAX_OVERLAY - 1] = [
+    Color::YELLOW,
+    Color::CYAN,
+    Color::RGB(255, 140, 0),
+    Color::RGB(255, 105, 180),
+    Color::RGB(150, 150, 255),
+    Color::WHITE,
+];
+/// Outlines of the slot the newest element went into and the one the last old element was copied into
+const WRITE_CURSOR_COLOR: Color = Color::RED;
+const COPY_CURSOR_COLOR: Color = Color::YELLOW;
+/// Outline of the part of the minimap the grid shows
+const MINIMAP_VIEW_COLOR: Color = Color::RED;
+/// Lines of the `--tiers` size classes in the capacity vs size graph
+const TIER_COLOR: Color = Color::RGB(90, 90, 90);
+/// Capacity line of the real `Vec` of `--compare-std` in the capacity vs size graph
+const STD_COLOR: Color = Color::RGB(255, 140, 0);
+/// Size line of the capacity vs size graph, capacities get drawn in [`SERIES_COLORS`]
+const SIZE_COLOR: Color = Color::WHITE;
+
+fn main() {
+    let config = config::Config::from_args().unwrap_or_else(|e| {
+        warn!("{}", e);
+        std::process::exit(1);
+    });
+    log::set_quiet(config.quiet);
+    if config.logs_to_stderr() {
+        log::use_stderr();
     }
-    
-    fn extend(&mut self) {
-        self.resizes += 1;
-        self.old_data_size = self.size;
-        self.capacity = (self.capacity as f64 * self.growth).ceil() as usize;
-        
-        if let Some(limit) = self.hard_limit {
-            if self.capacity > limit {
-                self.capacity = limit;
-            }
-        }
-        self.old_data_appended = 0;
+    // into stderr no matter what, it's for repeating the run and not part of what it outputs
+    if !config.show_version {
+        warn!("Options: {}", if config.effective_options.is_empty() { "none, everything at its default" } else { &config.effective_options });
+    }
+    if config.show_version {
+        info!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        return;
     }
 
-    fn append_old_data(&mut self) -> Result<usize, ()> {
-        if self.old_data_appended < self.old_data_size {
-            self.copy_operations += 1;
-            self.old_data_appended += 1;
-            Ok(self.old_data_appended)
-        } else {
-            Err(())
+    if config.benchmark {
+        let (from, to, step) = config.sweep;
+        let mut rows = benchmark::growths(from, to, step).into_iter().map(|growth| benchmark::run(growth, config.elements)).collect::<Vec<_>>();
+        benchmark::sort(&mut rows, config.sort_by);
+        benchmark::write_table(&rows, &mut std::io::stdout().lock()).unwrap();
+        if let Some(path) = config.csv.as_deref() {
+            if let Err(e) = std::fs::File::create(path).and_then(|file| benchmark::write_csv(&rows, &mut std::io::BufWriter::new(file))) {
+                warn!("Failed to write {} ({})", path, e);
+            }
         }
+        return;
     }
-}
 
-fn main() {
+    // cell size for the initial window, the number of cells stays the same when resizing (they just get drawn smaller or bigger).
+    // whatever doesn't fit a whole cell is left over, the layout works out the pixels from the number of cells
+    let grid_width = GRID_PIXELS as usize / config.cell_size;
+    let grid_height = GRID_PIXELS as usize / config.cell_size;
+
+    // deterministic runs stick to a fixed seed unless told otherwise
+    let seed = config.seed.unwrap_or_else(|| if config.deterministic { 0 } else { rand::random() });
+    if config.seed.is_none() && !config.deterministic && (config.inplace_prob > 0.0 || config.insert_rate.is_some()) {
+        info!("Using seed {} (pass --seed to reproduce this run)", seed);
+    }
 
-    let cell_size = 10usize;
-    let grid_width = 1000 / cell_size;
-    let grid_height = 1000 / cell_size;
+    // a replay brings its own growth factors, one array per factor in the order they show up in the file
+    let mut replay = config.replay.as_deref().map(|path| {
+        let rows = metrics::read_rows(path).unwrap_or_else(|e| {
+            warn!("{}", e);
+            std::process::exit(1);
+        });
+        let mut series: Vec<(f64, std::collections::VecDeque<metrics::Row>)> = vec![];
+        for row in rows {
+            match series.iter_mut().find(|(growth, _)| *growth == row.growth) {
+                Some((_, rows)) => rows.push_back(row),
+                None => series.push((row.growth, std::collections::VecDeque::from([row]))),
+            }
+        }
+        if series.is_empty() || series.len() > SERIES_COLORS.len() {
+            warn!("{} has to have rows for one or two growth factors", path);
+            std::process::exit(1);
+        }
+        series
+    });
 
-    let mut array = Array::new(std::env::args().nth(1).unwrap_or("1.618".to_string()).parse::<f64>().unwrap(), Some(grid_height * grid_width));
+    // every array gets its own horizontal band of the grid and its own stat panel
+    let mut growths: Vec<f64> = match &replay {
+        Some(series) => series.iter().map(|(growth, _)| *growth).collect(),
+        None if config.compare.is_empty() => vec![config.growth],
+        None => config.compare.clone(),
+    };
+    let label = match &replay {
+        Some(_) => growths.iter().map(f64::to_string).collect::<Vec<_>>().join("_vs_") + "_replay",
+        None => config.growth_label.clone(),
+    };
+    let band_height = grid_height / growths.len();
+    let build_sim = |growth: f64, vec: bool| {
+        let hard_limit = match (config.fixed, config.hard_limit) {
+            // a real Vec can't be capped, it runs until the grid is full
+            _ if vec => None,
+            (Some(capacity), _) => Some(capacity),
+            (None, config::HardLimit::Grid) => Some(band_height * grid_width),
+            (None, config::HardLimit::Fixed(limit)) => Some(limit),
+            (None, config::HardLimit::Unlimited) => None,
+        };
+        let array = Array::new(growth, hard_limit)
+            .with_initial_capacity(config.fixed.unwrap_or(config.initial_capacity))
+            .with_shrink_threshold(config.shrink_threshold)
+            .with_element_size(config.element_size)
+            .with_copy_cost(config.element_cost)
+            .with_copy_model(config.copy_model);
+        let sim = Simulation::new(array);
+        let sim = if vec { sim.with_vec_backend() } else { sim };
+        sim.with_copies_per_frame(config.copies_per_frame)
+            .with_appends_per_frame(config.appends_per_frame)
+            .with_inplace_prob(config.inplace_prob, seed)
+            .with_insert_rate(config.insert_rate, seed)
+            .with_deterministic(config.deterministic)
+            .with_visible_cells(band_height * grid_width)
+            .with_target(config.target)
+            .with_script(config.script.clone())
+            .with_growth_schedule(config.growth_schedule.clone())
+            .with_tiers(config.tiers.clone())
+            .with_hold_seconds(config.hold_seconds)
+            .with_bulk(config.bulk)
+            .with_reserve_at(if vec { vec![] } else { config.reserve_at.clone() })
+            // [ and ] move away from the preset
+            .with_preset(config.growth_preset.filter(|_| growth == config.growth && !vec))
+    };
+    let new_sim = |&growth: &f64| build_sim(growth, config.vec);
+    let mut sims = growths.iter().map(new_sim).collect::<Vec<_>>();
+    // a real Vec running along with the model, only its capacity is plotted
+    let replaying = replay.is_some();
+    let new_std_sim = || (config.compare_std && !replaying).then(|| build_sim(config.growth, true));
+    let mut std_sim = new_std_sim();
+    // the rest of the overlay factors run in lockstep without being drawn, only their efficiency is plotted
+    let mut overlay_growths = if replay.is_none() { config.overlay.iter().skip(1).copied().collect::<Vec<_>>() } else { vec![] };
+    let mut overlays = overlay_growths.iter().map(new_sim).collect::<Vec<_>>();
     let ctx = sdl2::init().unwrap();
     let video = ctx.video().unwrap();
     let mut event_pump = ctx.event_pump().unwrap();
-    let window = video.window("Array", 1600, 1000).position_centered().build().unwrap();
-    let mut canvas = window.into_canvas().accelerated().build().unwrap();
+    let window = video.window("Array", WINDOW_WIDTH, WINDOW_HEIGHT).position_centered().resizable().build().unwrap();
+    let mut canvas = window.into_canvas().accelerated();
+    if config.vsync {
+        canvas = canvas.present_vsync();
+    }
+    let mut canvas = canvas.build().unwrap();
     let texture_creator = canvas.texture_creator();
 
+    let mut sound = config.sound.then(|| {
+        ctx.audio()
+            .and_then(|audio| sound::Sound::new(&audio))
+            .map_err(|e| warn!("Failed to open audio ({}), continuing without sound", e))
+            .ok()
+    }).flatten();
+
     let ttf = sdl2::ttf::init().unwrap();
-    let font = ttf.load_font_from_rwops(RWops::from_bytes(include_bytes!("../Sen-Regular.ttf")).unwrap(), 30).unwrap();
+    // the embedded font unless another one was given
+    let load_font = |size: u16| match &config.font {
+        Some(path) => ttf.load_font(path, size),
+        None => ttf.load_font_from_rwops(RWops::from_bytes(include_bytes!("../Sen-Regular.ttf")).unwrap(), size),
+    };
+    let font = load_font(config.font_size).unwrap_or_else(|e| {
+        warn!("Failed to load the font ({})", e);
+        std::process::exit(1);
+    });
+    // for the legend, and for the stat panels when comparing since two of them don't fit with the normal font
+    let small_font = load_font((config.font_size * 2 / 3).max(1)).unwrap();
+    let panel_font = if sims.len() > 1 { &small_font } else { &font };
 
-    let mut operations_per_append = 0.0;
-    let mut memory_efficiency;
-    let mut operations = 0;
+    let video = format!("{}.mp4", label);
+    #[cfg(feature = "recording")]
+    let mut recorder = start_recording(&config, &video);
+    #[cfg(feature = "recording")]
+    let mut recording = recorder.is_recording();
+    #[cfg(not(feature = "recording"))]
+    let recording = false;
+    #[cfg(not(feature = "recording"))]
+    if config.record {
+        info!("Built without the recording feature, so there's no video. --png-dir and --raw-stdout still write the frames");
+    }
+    if recording {
+        info!("Recording will start once started simulation...");
+        // the window shows max_fps frames a second and every record_every-th of them gets recorded
+        match config.max_fps {
+            Some(max_fps) if u64::from(max_fps) == u64::from(config.record_fps) * config.record_every => {}
+            Some(max_fps) if u64::from(max_fps).is_multiple_of(config.record_every) => info!(
+                "The recording is {} FPS, --record-fps {} makes it play at the same speed as the window",
+                config.record_fps,
+                u64::from(max_fps) / config.record_every
+            ),
+            _ => info!("The recording is {} FPS, it won't play at the same speed as the window", config.record_fps),
+        }
+    }
+    // frames as PNGs instead of a video, numbered from 1
+    let mut png_dir = config.png_dir.as_ref().map(std::path::PathBuf::from).filter(|dir| {
+        std::fs::create_dir_all(dir).map_err(|e| warn!("Failed to create {} ({}), not writing frames", dir.display(), e)).is_ok()
+    });
+    let mut png_frames: u64 = 0;
+    // raw RGB24 frames for piping into something else, the log moved to stderr so it doesn't end up in between
+    let mut raw_stdout = config.raw_stdout.then(std::io::stdout);
+    if raw_stdout.is_some() {
+        info!(
+            "Writing {}x{} RGB24 frames at {} FPS into stdout, like for ffmpeg -f rawvideo -pix_fmt rgb24 -s {}x{} -r {} -i -",
+            config.record_width, config.record_height, config.record_fps, config.record_width, config.record_height, config.record_fps
+        );
+    }
+    // Ctrl-C just asks the loop to stop so everything gets finalized properly, a second one exits right away
+    let should_quit = Arc::new(AtomicBool::new(false));
+    #[cfg(feature = "recording")]
+    {
+        let handler_quit = Arc::clone(&should_quit);
+        ctrlc::set_handler(move || {
+            if handler_quit.swap(true, Ordering::SeqCst) {
+                std::process::exit(130);
+            }
+            info!("Stopping, press Ctrl-C again to exit right away");
+        })
+        .expect("Failed to listen for CTRL-C");
+    }
+
+    let mut csv = config.csv.as_deref().and_then(|path| {
+        metrics::CsvWriter::new(path)
+            .map_err(|e| warn!("Failed to create {} ({}), not writing metrics", path, e))
+            .ok()
+    });
+
+    let mut event_log = config.log_events.as_deref().and_then(|path| {
+        metrics::EventLog::new(path)
+            .map_err(|e| warn!("Failed to create {} ({}), not logging resizes", path, e))
+            .ok()
+    });
+
+    #[cfg(debug_assertions)]
+    let mut fps = fps::FpsCounter::new();
+    // every frame becomes a frame of the recording, so capping at its frame rate keeps the video in real time
+    let frame_budget = config.max_fps.map(|max_fps| std::time::Duration::from_secs_f64(1.0 / max_fps as f64));
+    let mut next_frame = std::time::Instant::now();
+
+    let mut frames_drawn: u64 = 0;
+    // set by S, the frame gets saved once it's drawn
+    let mut screenshot_requested = false;
+    let mut log_scale = config.log_scale;
+    // when and on which frame the intro card or a resize with --explain paused and for how many seconds,
+    // nothing moves until it runs out or space is pressed
+    let mut paused_at: Option<(std::time::Instant, u64, f64)> = None;
+    let mut explanation = (String::new(), vec![]);
+    // capacities from before the resize and the frame it happened on, while --animate-resize sweeps the new slots in
+    let mut growing: Option<(Vec<usize>, u64)> = None;
+    // message shown over the grid for a moment, like the new growth factor after [ or ]
+    let mut toast: Option<(String, std::time::Instant)> = None;
+    if config.intro_seconds > 0.0 {
+        paused_at = Some((std::time::Instant::now(), 0, config.intro_seconds));
+        explanation = (format!("{} v{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")), render::intro_lines(&config));
+    }
+
+    // the recording keeps its size when the window changes, said once so dragging the border doesn't flood the log
+    let mut told_about_resize = false;
 
-    let ffmpeg = Arc::new(Mutex::new(ffmpeg::VideoRecorder::new(&(std::env::args().nth(1).unwrap_or("2.0".to_string()) + ".mp4"), 1600, 1000, 60)));
-    let cloned_vr = std::sync::Arc::clone(&ffmpeg.clone());
-    println!("Recording will start once started simulation...");
-    ctrlc::set_handler(move || {
-        cloned_vr.lock().unwrap().kill();
-    })
-    .expect("Failed to listen for CTRL-C (Force exiting with FFMpeg)");
-
-    // fps stuff
-    let mut ft = std::time::Instant::now(); // frame time
-    let mut fc = 0; // frame count
-    let mut fps = 0.0; // frame per sec
-    let mut mf = 0.0; // maximum fps
-    let mut lf = 0.0; // minimum fps (shows on screen)
-    let mut lpf = 0.0; // act as a cache
-    let mut lft = std::time::Instant::now(); // minimum frame refresh time thingy
-
-    let mut all_efficiencies = vec![];
-    let mut all_appends = vec![];
-
-    let mut limited_reached = false;
-    let mut last_limit_reached = std::time::Instant::now();
-    
     'running: loop {
+        if should_quit.load(Ordering::SeqCst) {
+            break 'running;
+        }
+
         for event in event_pump.poll_iter() {
-            if let sdl2::event::Event::Quit {..} = event { break 'running }
+            match event {
+                Event::Quit {..} => break 'running,
+                Event::Window { win_event: sdl2::event::WindowEvent::SizeChanged(width, height), .. }
+                    if (recording || png_dir.is_some() || raw_stdout.is_some()) && !told_about_resize =>
+                {
+                    told_about_resize = true;
+                    info!(
+                        "Window resized to {}x{}, the recording stays {}x{} with every frame scaled to fit",
+                        width, height, config.record_width, config.record_height
+                    );
+                }
+                Event::KeyDown { keycode: Some(Keycode::S), .. } => screenshot_requested = true,
+                Event::KeyDown { keycode: Some(Keycode::Space | Keycode::Return), .. } => paused_at = None,
+                Event::KeyDown { keycode: Some(Keycode::L), .. } => log_scale = !log_scale,
+                // the layout follows the window size every frame and the recording scales to fit, so nothing else changes
+                Event::KeyDown { keycode: Some(Keycode::F11), .. } => {
+                    let window = canvas.window_mut();
+                    let fullscreen = if window.fullscreen_state() == FullscreenType::Off { FullscreenType::Desktop } else { FullscreenType::Off };
+                    if let Err(e) = window.set_fullscreen(fullscreen) {
+                        warn!("Failed to toggle fullscreen ({})", e);
+                    }
+                }
+                // nudge the growth factors and start over, a replay is stuck with the ones it recorded
+                Event::KeyDown { keycode: Some(key @ (Keycode::LeftBracket | Keycode::RightBracket)), .. } if replay.is_none() => {
+                    let step = if key == Keycode::RightBracket { GROWTH_STEP } else { -GROWTH_STEP };
+                    let nudged = |growth: &f64| ((growth + step) * 1e9).round() / 1e9;
+                    if growths.iter().chain(&overlay_growths).all(|growth| nudged(growth) > 1.0) {
+                        growths = growths.iter().map(nudged).collect();
+                        overlay_growths = overlay_growths.iter().map(nudged).collect();
+                        // starting over keeps the speed picked with + and -
+                        let appends_per_frame = sims.first().map_or(config.appends_per_frame, |sim| sim.appends_per_frame);
+                        sims = growths.iter().map(new_sim).collect();
+                        overlays = overlay_growths.iter().map(new_sim).collect();
+                        std_sim = new_std_sim();
+                        for sim in sims.iter_mut().chain(overlays.iter_mut()).chain(std_sim.as_mut()) {
+                            sim.appends_per_frame = appends_per_frame;
+                        }
+                        if let Some(log) = event_log.as_mut() {
+                            log.restart();
+                        }
+                        paused_at = None;
+                        let factors = growths.iter().chain(&overlay_growths).map(f64::to_string).collect::<Vec<_>>().join(", ");
+                        toast = Some((format!("Growth factor {}", factors), std::time::Instant::now()));
+                    }
+                }
+                // speed up or slow down the fill, overlays and the std::Vec too so they stay in lockstep
+                Event::KeyDown { keycode: Some(Keycode::Plus | Keycode::Equals | Keycode::KpPlus), .. } => {
+                    for sim in sims.iter_mut().chain(overlays.iter_mut()).chain(std_sim.as_mut()) {
+                        sim.appends_per_frame += 1;
+                    }
+                }
+                Event::KeyDown { keycode: Some(Keycode::Minus | Keycode::KpMinus), .. } => {
+                    for sim in sims.iter_mut().chain(overlays.iter_mut()).chain(std_sim.as_mut()) {
+                        sim.appends_per_frame = (sim.appends_per_frame - 1).max(1);
+                    }
+                }
+                _ => {}
+            }
         }
 
-        if last_limit_reached.elapsed().as_secs() >= 3 && limited_reached {
+        if sims.iter().all(Simulation::finished) {
             break 'running;
         }
 
+        canvas.set_draw_color(config.theme.background);
         canvas.clear();
-        
-        
-        memory_efficiency = ((array.size as f64 - array.old_data_size as f64) + array.old_data_appended as f64) / (array.capacity as f64);
-        if !limited_reached {
-            all_efficiencies.push(memory_efficiency);
-        }
-        
-        for x in 0..grid_width {
-            for y in 0..grid_height {
-                let rect = Rect::new(x as i32 * cell_size as i32, y as i32 * cell_size as i32, cell_size as u32, cell_size as u32);
-                let index = x + y * grid_width;
-                if array.capacity >= index {
-                    // are in range of allocated memory
-                    // checks if data size is in range of the position
-                    if array.size >= index && array.old_data_size <= index { // are not old data
-                        canvas.set_draw_color(Color::GREEN);
-                    } else if array.size >= index && array.old_data_size >= index { // are old data
-                        if index <= array.old_data_appended && !limited_reached {
-                            canvas.set_draw_color(Color::CYAN);
-                        } else {
-                            canvas.set_draw_color(Color::BLUE);
+
+        let (window_width, window_height) = canvas.output_size().unwrap();
+        let layout = render::Layout::new(window_width, window_height, grid_width, grid_height);
+        let layout = if config.show_freelist { layout.with_freelist() } else { layout };
+        // past the grid the cells aren't drawn, so once an array outgrows it the whole capacity shows up scaled down
+        let overflowing = config.map_layout == config::MapLayout::Grid && sims.iter().any(|sim| sim.array.capacity > sim.visible_cells);
+        let layout = if overflowing { layout.with_minimap(sims.len()) } else { layout };
+
+        let resizes_before = sims.iter().map(|sim| sim.array.resizes).collect::<Vec<_>>();
+        let capacities_before = sims.iter().map(|sim| sim.array.capacity).collect::<Vec<_>>();
+        // deterministic runs count the pause in frames so the recording comes out the same every time
+        let paused = paused_at.is_some_and(|(since, frame, seconds)| {
+            if config.deterministic {
+                ((frames_drawn - frame) as f64) < seconds * simulation::TICKS_PER_SECOND as f64
+            } else {
+                since.elapsed().as_secs_f64() < seconds
+            }
+        });
+        // everything holds still until the new block is swept in, so the copy only starts after it
+        let animating = match (&growing, config.animate_resize) {
+            (Some((_, frame)), Some(frames)) => frames_drawn - frame < frames,
+            _ => false,
+        };
+        if !animating {
+            growing = None;
+        }
+        if !paused && !animating {
+            paused_at = None;
+            match replay.as_mut() {
+                Some(series) => {
+                    for (sim, (_, rows)) in sims.iter_mut().zip(series.iter_mut()) {
+                        sim.replay(rows.pop_front().as_ref());
+                    }
+                }
+                None => {
+                    for sim in sims.iter_mut().chain(overlays.iter_mut()).chain(std_sim.as_mut()) {
+                        sim.tick();
+                        // panicking still finishes the recording, so the video shows what led up to it
+                        if let Some(Err(e)) = config.check_invariants.then(|| sim.check_invariants()) {
+                            panic!("Broken invariant at tick {} of growth factor {}: {}\n{:#?}", sim.ticks, sim.array.growth, e, sim.array);
                         }
-                    } else { // still empty space
-                        canvas.set_draw_color(Color::BLACK);
                     }
-                    canvas.fill_rect(rect).unwrap();
                 }
             }
-        }
-        
-        match array.grow() {
-            Err(_) => {
-                if array.old_data_appended == array.old_data_size {
-                    println!("\rExpanding array's capacity by allocating more memory");
-                    array.extend();
-                    println!("New capacity: {}", array.capacity);
-                    if array.grow().is_err() && !limited_reached {
-                        limited_reached = true;
-                        last_limit_reached = std::time::Instant::now();
-                    }
-                    operations += 2;
+            if config.animate_resize.is_some() && sims.iter().zip(&capacities_before).any(|(sim, &before)| sim.array.capacity > before) {
+                growing = Some((capacities_before, frames_drawn));
+            }
+            if config.explain {
+                if let Some(sim) = sims.iter().zip(&resizes_before).find(|(sim, &before)| sim.array.resizes > before).map(|(sim, _)| sim) {
+                    paused_at = Some((std::time::Instant::now(), frames_drawn, config.explain_pause));
+                    explanation = ("Resize".to_string(), render::explain_lines(sim));
                 }
-            },
-            Ok(_) => {
-                print!("\rSuccessfully appended new data: {}", array.size);
-                operations += 1;
             }
         }
 
-        if array.append_old_data().is_ok() && !limited_reached {
-            debuggery!("\rSuccessfully appended old data: {}", array.old_data_appended);
-            operations += 1;
+        if let Some(sound) = sound.as_mut() {
+            if let Some(sim) = sims.iter().zip(&resizes_before).find(|(sim, &before)| sim.array.resizes > before).map(|(sim, _)| sim) {
+                sound.beep(sim.array.capacity);
+            }
         }
 
-        if !limited_reached {
-            operations_per_append = operations as f64 / 1.0;
-            operations = 0;
-            all_appends.push(operations_per_append);
+        // a row per tick, nothing ticks while paused
+        if let Some(writer) = csv.as_mut().filter(|_| !paused && !animating) {
+            let written = sims.iter().filter(|sim| !sim.limited_reached).try_for_each(|sim| writer.write_row(sim));
+            if let Err(e) = written {
+                warn!("Failed to write metrics ({}), not writing any more", e);
+                csv = None;
+            }
         }
 
-        let mut starting_y = (canvas.logical_size().1 / {
-            #[cfg(debug_assertions)]
-            {
-                10
+        if let Some(Err(e)) = event_log.as_mut().map(|log| log.write_new(&sims)) {
+            warn!("Failed to log resizes ({}), not logging any more", e);
+            event_log = None;
+        }
+
+        for (i, sim) in sims.iter().enumerate() {
+            let band = Rect::new(layout.grid.x(), layout.grid.y() + (i * band_height * layout.cell_size) as i32, layout.grid.width(), (band_height * layout.cell_size) as u32);
+            let array = match (&growing, config.animate_resize) {
+                (Some((before, frame)), Some(frames)) => render::growing_into(&sim.array, before[i], (frames_drawn - frame) as f64 / frames as f64),
+                _ => sim.array,
+            };
+            match config.map_layout {
+                config::MapLayout::Grid => render::draw_memory_map(&mut canvas, &array, sim.limited_reached, band, layout.cell_size, &config.theme.palette, config.gradient).unwrap(),
+                config::MapLayout::Linear => render::draw_linear_map(&mut canvas, &array, sim.limited_reached, band, &config.theme.palette, config.gradient).unwrap(),
+                config::MapLayout::History => render::draw_history_map(&mut canvas, sim, band, &config.theme.palette).unwrap(),
             }
-            #[cfg(not(debug_assertions))]
-            {
-                7
+            // popped slots flash red and fade back to empty
+            for (index, t) in sim.recently_removed() {
+                let color = render::removed_color(config.theme.palette.empty, t);
+                match config.map_layout {
+                    config::MapLayout::Grid => render::fill_grid_cell(&mut canvas, index, band, layout.cell_size, color).unwrap(),
+                    config::MapLayout::Linear => render::draw_linear_cursor(&mut canvas, &sim.array, index, band, color).unwrap(),
+                    config::MapLayout::History => {}
+                }
             }
-        }) as i32 + ((font.size_of("a").unwrap().1) * {
-            #[cfg(debug_assertions)]
-            {
-                10
+            // where the last write and copy went, the history is too squashed to point at single slots
+            for (index, color) in [(sim.last_write, WRITE_CURSOR_COLOR), (sim.last_copy, COPY_CURSOR_COLOR)].into_iter().filter(|_| config.map_layout != config::MapLayout::History) {
+                let Some(index) = index else { continue };
+                match config.map_layout {
+                    config::MapLayout::Grid => render::draw_grid_cursor(&mut canvas, index, band, layout.cell_size, color).unwrap(),
+                    config::MapLayout::Linear => render::draw_linear_cursor(&mut canvas, &sim.array, index, band, color).unwrap(),
+                    config::MapLayout::History => {}
+                }
             }
-            #[cfg(not(debug_assertions))]
-            {
-                7
+            // a static array just stops taking elements once it's full
+            if config.fixed.is_some() && sim.limited_reached {
+                let (width, height) = font.size_of("FULL").unwrap();
+                let center = band.center();
+                render::draw_text(&mut canvas, &font, &texture_creator, "FULL", center.x() - width as i32 / 2, center.y() - height as i32 / 2, Color::RED).unwrap();
             }
-        } / 2) as i32;
-
-        let mem_eff = font.render(&format!("Memory efficiency: {:.3}%", memory_efficiency * 100.0)).blended(Color::BLACK).unwrap();
-        let op_append = font.render(&format!("Operations per append: {:.3}", operations_per_append)).blended(Color::BLACK).unwrap();
-        let capacity = font.render(&format!("Capacity: {}", array.capacity)).blended(Color::BLACK).unwrap();
-        let size = font.render(&format!("Size: {}", array.size)).blended(Color::BLACK).unwrap();
-        let gf = font.render(&format!("Growth factor: {}", array.growth)).blended(Color::BLACK).unwrap();
-        let all_eff = font.render(&format!("All efficiencies: {:.3}%", all_efficiencies.iter().sum::<f64>() / all_efficiencies.len() as f64 * 100.0)).blended(Color::BLACK).unwrap();
-        let all_append = font.render(&format!("All appends: {:.3}", all_appends.iter().sum::<f64>() / all_appends.len() as f64)).blended(Color::BLACK).unwrap();
-        let copy_operations = font.render(&format!("Copy operations: {}", array.copy_operations)).blended(Color::BLACK).unwrap();
-        let resizes = font.render(&format!("Resizes: {}", array.resizes)).blended(Color::BLACK).unwrap();
-        let copy_ops_per_resize = font.render(&format!("Copy operations per resize: {:.3}", array.copy_operations as f64 / array.resizes as f64)).blended(Color::BLACK).unwrap();
-
-        canvas.copy(&mem_eff.as_texture(&texture_creator).unwrap(), None, Some(Rect::new(1000, starting_y, mem_eff.width(), mem_eff.height()))).unwrap();
-        starting_y += mem_eff.height() as i32;
-        canvas.copy(&op_append.as_texture(&texture_creator).unwrap(), None, Some(Rect::new(1000, starting_y, op_append.width(), op_append.height()))).unwrap();
-        starting_y += op_append.height() as i32;
-        canvas.copy(&capacity.as_texture(&texture_creator).unwrap(), None, Some(Rect::new(1000, starting_y, capacity.width(), capacity.height()))).unwrap();
-        starting_y += capacity.height() as i32;
-        canvas.copy(&size.as_texture(&texture_creator).unwrap(), None, Some(Rect::new(1000, starting_y, size.width(), size.height()))).unwrap();
-        starting_y += size.height() as i32;
-        canvas.copy(&gf.as_texture(&texture_creator).unwrap(), None, Some(Rect::new(1000, starting_y, gf.width(), gf.height()))).unwrap();
-        starting_y += gf.height() as i32;
-        canvas.copy(&all_eff.as_texture(&texture_creator).unwrap(), None, Some(Rect::new(1000, starting_y, all_eff.width(), all_eff.height()))).unwrap();
-        starting_y += all_eff.height() as i32;
-        canvas.copy(&all_append.as_texture(&texture_creator).unwrap(), None, Some(Rect::new(1000, starting_y, all_append.width(), all_append.height()))).unwrap();
-        starting_y += all_append.height() as i32;
-        canvas.copy(&copy_operations.as_texture(&texture_creator).unwrap(), None, Some(Rect::new(1000, starting_y, copy_operations.width(), copy_operations.height()))).unwrap();
-        starting_y += copy_operations.height() as i32;
-        canvas.copy(&resizes.as_texture(&texture_creator).unwrap(), None, Some(Rect::new(1000, starting_y, resizes.width(), resizes.height()))).unwrap();
-        starting_y += resizes.height() as i32;
-        canvas.copy(&copy_ops_per_resize.as_texture(&texture_creator).unwrap(), None, Some(Rect::new(1000, starting_y, copy_ops_per_resize.width(), copy_ops_per_resize.height()))).unwrap();
-        starting_y += copy_ops_per_resize.height() as i32;
+        }
 
+        // efficiency over time, lower right corner below the stats with the legend right above it
+        let graph_title_y = layout.graph.y() - font.height();
+        let legend_y = graph_title_y - small_font.height() - 10;
+        render::draw_legend(&mut canvas, &small_font, &texture_creator, layout.graph.x(), legend_y, &config.theme).unwrap();
+        render::draw_text(&mut canvas, &font, &texture_creator, "Efficiency over time", layout.graph.x(), graph_title_y, config.theme.text).unwrap();
+
+        // the stats of every array one after another between the histogram and the legend
+        let mut lines = vec![];
+        for sim in &sims {
+            lines.extend(render::panel_lines(sim, &config.metrics, config.theme.text));
+            lines.push((String::new(), config.theme.text));
+        }
+        // picking the lines with --metrics leaves these out too
         #[cfg(debug_assertions)]
+        if config.metrics == config::Metric::all() {
+            lines.extend([
+                format!("Minimum FPS: {:.2}", fps.min),
+                format!("Maximum FPS: {:.2}", fps.max),
+                format!("Current FPS: {:.2}", fps.current),
+            ].map(|line| (line, config.theme.text)));
+        }
+        let panel = Rect::new(layout.panel.x(), layout.panel.y(), layout.panel.width(), (legend_y - 10 - layout.panel.y()).max(1) as u32);
+        render::draw_panel(&mut canvas, panel_font, &texture_creator, &lines, panel).unwrap();
 
-        {
-            let min_fps = font.render(&format!("Minimum FPS: {:.2}", lf)).blended(Color::BLACK).unwrap();
-            let max_fps = font.render(&format!("Maximum FPS: {:.2}", mf)).blended(Color::BLACK).unwrap();
-            let cur_fps = font.render(&format!("Current FPS: {:.2}", fps)).blended(Color::BLACK).unwrap();
-            canvas.copy(&min_fps.as_texture(&texture_creator).unwrap(), None, Some(Rect::new(1000, starting_y, min_fps.width(), min_fps.height()))).unwrap();
-            starting_y += min_fps.height() as i32;
-            canvas.copy(&max_fps.as_texture(&texture_creator).unwrap(), None, Some(Rect::new(1000, starting_y, max_fps.width(), max_fps.height()))).unwrap();
-            starting_y += max_fps.height() as i32;
-            canvas.copy(&cur_fps.as_texture(&texture_creator).unwrap(), None, Some(Rect::new(1000, starting_y, cur_fps.width(), cur_fps.height()))).unwrap();
-        };
+        canvas.set_draw_color(config.theme.graph_background);
+        canvas.fill_rect(layout.graph).unwrap();
+        // a resize leaves the array about 1/g full, that's where the troughs land with --copy-model instant.
+        // incremental copies dip below it until the old data catches up. taken from the current factor
+        // every frame, so it follows [ and ] and growth schedules
+        for (sim, color) in sims.iter().zip(SERIES_COLORS) {
+            let growth = if sim.vec_backed() { 2.0 } else { sim.array.growth };
+            graph::draw_marker(&mut canvas, layout.graph, 1.0 / growth, 0.0, 1.0, render::shade(color, 0.0)).unwrap();
+        }
+        for (sim, color) in sims.iter().zip(SERIES_COLORS) {
+            graph::draw_line_graph(&mut canvas, layout.graph, &sim.all_efficiencies, 0.0, 1.0, color).unwrap();
+        }
+        for (sim, color) in overlays.iter().zip(OVERLAY_COLORS) {
+            graph::draw_line_graph(&mut canvas, layout.graph, &sim.all_efficiencies, 0.0, 1.0, color).unwrap();
+        }
+        if !overlays.is_empty() {
+            // which color is which factor, in the corner of the graph
+            let mut x = layout.graph.x() + 4;
+            for (sim, color) in sims.iter().zip(SERIES_COLORS).chain(overlays.iter().zip(OVERLAY_COLORS)) {
+                let label = sim.array.growth.to_string();
+                render::draw_text(&mut canvas, &small_font, &texture_creator, &label, x, layout.graph.y() + 2, color).unwrap();
+                x += small_font.size_of(&label).unwrap().0 as i32 + 12;
+            }
+        }
+
+        // capacity staircase against the size growing linearly below it, the gap between them is wasted space.
+        // on a log scale every resize of a geometric growth is an equally tall step
+        let title = if log_scale { "Capacity vs size (log)" } else { "Capacity vs size" };
+        render::draw_text(&mut canvas, &small_font, &texture_creator, title, layout.size_graph.x(), layout.size_graph.y() - small_font.height(), config.theme.text).unwrap();
+        canvas.set_draw_color(config.theme.graph_background);
+        canvas.fill_rect(layout.size_graph).unwrap();
+        let peak = sims.iter().chain(&std_sim).map(|sim| sim.peak_capacity).max().unwrap_or(1) as f64;
+        // size class boundaries the capacity snaps to
+        for &tier in &config.tiers {
+            let (value, max) = if log_scale { ((tier as f64).log2(), peak.max(2.0).log2()) } else { (tier as f64, peak) };
+            graph::draw_marker(&mut canvas, layout.size_graph, value, 0.0, max, TIER_COLOR).unwrap();
+        }
+        for (sim, color) in sims.iter().zip(SERIES_COLORS).chain(std_sim.iter().map(|sim| (sim, STD_COLOR))) {
+            if log_scale {
+                let peak = peak.max(2.0).log2();
+                graph::draw_line_graph(&mut canvas, layout.size_graph, &graph::log2_samples(&sim.all_capacities), 0.0, peak, color).unwrap();
+                graph::draw_line_graph(&mut canvas, layout.size_graph, &graph::log2_samples(&sim.all_sizes), 0.0, peak, SIZE_COLOR).unwrap();
+            } else {
+                graph::draw_line_graph(&mut canvas, layout.size_graph, &sim.all_capacities, 0.0, peak, color).unwrap();
+                graph::draw_line_graph(&mut canvas, layout.size_graph, &sim.all_sizes, 0.0, peak, SIZE_COLOR).unwrap();
+            }
+        }
+
+        if std_sim.is_some() {
+            let mut x = layout.size_graph.x() + 4;
+            for (label, color) in [("model", SERIES_COLORS[0]), ("std::Vec", STD_COLOR)] {
+                render::draw_text(&mut canvas, &small_font, &texture_creator, label, x, layout.size_graph.y() + 2, color).unwrap();
+                x += small_font.size_of(label).unwrap().0 as i32 + 12;
+            }
+        }
+
+        // capacity of every resize bucketed by powers of two, geometric growth makes the bars even
+        let histogram = layout.histogram;
+        let series = sims.iter().zip(SERIES_COLORS).map(|(sim, color)| (stats::log2_buckets(&sim.resize_sizes, HISTOGRAM_BUCKETS), color)).collect::<Vec<_>>();
+        render::draw_text(&mut canvas, &small_font, &texture_creator, "Resize sizes", histogram.x(), histogram.y() - small_font.height(), config.theme.text).unwrap();
+        canvas.set_draw_color(config.theme.graph_background);
+        canvas.fill_rect(histogram).unwrap();
+        graph::draw_bar_chart(&mut canvas, histogram, &series).unwrap();
+        let buckets = series.iter().map(|(counts, _)| counts.len()).max().unwrap_or(0);
+        if buckets > 0 {
+            let last = render::thousands(1 << (buckets - 1)) + if buckets == HISTOGRAM_BUCKETS { "+" } else { "" };
+            let (last_width, _) = small_font.size_of(&last).unwrap();
+            render::draw_text(&mut canvas, &small_font, &texture_creator, "1", histogram.x(), histogram.bottom(), config.theme.text).unwrap();
+            render::draw_text(&mut canvas, &small_font, &texture_creator, &last, histogram.right() - last_width as i32, histogram.bottom(), config.theme.text).unwrap();
+        }
+
+        // copies of the last couple of seconds, they only ever happen in bursts right after a resize
+        let sparkline = layout.sparkline;
+        render::draw_text(&mut canvas, &small_font, &texture_creator, "Copies per tick", sparkline.x(), sparkline.y() - small_font.height(), config.theme.text).unwrap();
+        canvas.set_draw_color(config.theme.graph_background);
+        canvas.fill_rect(sparkline).unwrap();
+        let recent = sims.iter().map(|sim| sim.recent_copies.iter().map(|&c| c as f64).collect::<Vec<_>>()).collect::<Vec<_>>();
+        let highest = recent.iter().flatten().cloned().fold(1.0, f64::max);
+        for (copies, color) in recent.iter().zip(SERIES_COLORS) {
+            graph::draw_line_graph(&mut canvas, sparkline, copies, 0.0, highest, color).unwrap();
+        }
+
+        // resizes get rarer the bigger the array gets, so the marks spread out towards the right
+        let timeline = layout.timeline;
+        render::draw_text(&mut canvas, &small_font, &texture_creator, "Resizes over time", timeline.x(), timeline.y() - small_font.height(), config.theme.text).unwrap();
+        canvas.set_draw_color(config.theme.graph_background);
+        canvas.fill_rect(timeline).unwrap();
+        let row_height = (timeline.height() / sims.len() as u32).max(1);
+        for (i, (sim, color)) in sims.iter().zip(SERIES_COLORS).enumerate() {
+            let row = Rect::new(timeline.x(), timeline.y() + (i as u32 * row_height) as i32, timeline.width(), row_height);
+            render::draw_timeline(&mut canvas, sim.resize_ticks(), sim.elapsed().0, row, color).unwrap();
+        }
+
+        if let Some(strip) = layout.minimap {
+            render::draw_text(&mut canvas, &small_font, &texture_creator, "Whole array (outlined: shown in the grid)", strip.x(), strip.y() - small_font.height(), config.theme.text).unwrap();
+            for (i, sim) in sims.iter().enumerate() {
+                let row = render::minimap_row(strip, i);
+                render::draw_minimap(&mut canvas, &sim.array, sim.limited_reached, sim.visible_cells, row, &config.theme.palette, MINIMAP_VIEW_COLOR).unwrap();
+            }
+        }
+
+        // every resize hands its old block back, an allocator could reuse them for something else
+        if let Some(strip) = layout.freelist {
+            render::draw_text(&mut canvas, &small_font, &texture_creator, "Free list", strip.x(), strip.y() - small_font.height(), config.theme.text).unwrap();
+            canvas.set_draw_color(config.theme.graph_background);
+            canvas.fill_rect(strip).unwrap();
+            let row_height = (strip.height() / sims.len() as u32).max(1);
+            for (i, sim) in sims.iter().enumerate() {
+                let row = Rect::new(strip.x(), strip.y() + (i as u32 * row_height) as i32, strip.width(), row_height);
+                render::draw_freelist(&mut canvas, &sim.previous_allocations(), row).unwrap();
+            }
+        }
 
-        canvas.set_draw_color(Color::WHITE);
+        if let (config::MapLayout::Grid, Some(every)) = (config.map_layout, config.grid_every) {
+            render::draw_grid_lines(&mut canvas, layout.grid, layout.cell_size, every, config.theme.grid_lines).unwrap();
+        }
+        // red line between the bands so it's clear where one array ends and the next starts
+        canvas.set_draw_color(Color::RED);
+        for i in 1..sims.len() {
+            canvas.fill_rect(Rect::new(layout.grid.x(), layout.grid.y() + (i * band_height * layout.cell_size) as i32 - 1, layout.grid.width(), 3)).unwrap();
+        }
 
-        for x in 0..=grid_width {
-            let x_pos = x * cell_size;
-            canvas.draw_line((x_pos as i32, 0), (x_pos as i32, 1000)).unwrap();
+        // stays up for the whole pause so it ends up in the recording too
+        if paused_at.is_some() {
+            let (title, lines) = &explanation;
+            render::draw_overlay(&mut canvas, panel_font, &texture_creator, title, lines, layout.grid, &config.theme).unwrap();
         }
-        for y in 0..=grid_height {
-            let y_pos = y * cell_size;
-            canvas.draw_line((0, y_pos as i32), (1000, y_pos as i32)).unwrap();
+
+        if let Some((message, since)) = &toast {
+            if since.elapsed() < TOAST_DURATION {
+                render::draw_overlay(&mut canvas, panel_font, &texture_creator, message, &[], layout.grid, &config.theme).unwrap();
+            } else {
+                toast = None;
+            }
         }
 
-        canvas.set_draw_color(Color::GRAY);
+        // held for the last few seconds before the window closes
+        if sims.iter().all(Simulation::holding) {
+            let lines = sims.iter().map(render::summary_lines).collect::<Vec<_>>().join(&String::new());
+            render::draw_overlay(&mut canvas, panel_font, &texture_creator, "Summary", &lines, layout.grid, &config.theme).unwrap();
+        }
 
         canvas.present();
 
-        fc += 1;
-        let elapsed_time = ft.elapsed();
-        if elapsed_time.as_secs() >= 1 {
-            fps = fc as f64 / elapsed_time.as_secs_f64();
-            fc = 0;
-            ft = std::time::Instant::now();
-            if fps > mf {
-                mf = fps
-            } else if fps < lpf {
-                lpf = fps
+        #[cfg(debug_assertions)]
+        fps.frame();
+        if screenshot_requested {
+            screenshot_requested = false;
+            let saved = canvas
+                .read_pixels(None, sdl2::pixels::PixelFormatEnum::RGB24)
+                .and_then(|pixels| screenshot::save(&pixels, window_width, window_height));
+            match saved {
+                Ok(path) => info!("Saved screenshot as {}", path),
+                Err(e) => warn!("Failed to save screenshot ({})", e),
             }
         }
-        let elapsed_time = lft.elapsed();
-        if elapsed_time.as_secs() >= 3 {
-            lf = lpf;
-            lpf = fps;
-            lft = std::time::Instant::now();
-        }
-        let mut v = ffmpeg.lock().unwrap();
-                v.process_frame(
-                    canvas
-                        .read_pixels(
-                            sdl2::rect::Rect::new(0, 0, 1600, 1000),
-                            sdl2::pixels::PixelFormatEnum::RGB24,
-                        )
-                        .unwrap(),
-                );
+        // the whole window scaled to the recording size, whatever size the window has right now
+        // only every nth frame with --record-every, a time-lapse unless --record-fps slows the video down to match
+        if (recording || png_dir.is_some() || raw_stdout.is_some()) && frames_drawn.is_multiple_of(config.record_every) {
+            let frame = canvas.read_pixels(None, sdl2::pixels::PixelFormatEnum::RGB24).unwrap();
+            let frame = screenshot::fit_frame(frame, window_width, window_height, config.record_width, config.record_height);
+            if let Some(dir) = &png_dir {
+                png_frames += 1;
+                if let Err(e) = screenshot::save_frame(dir, png_frames, &frame, config.record_width, config.record_height) {
+                    warn!("Failed to write frame {} ({}), not writing any more", png_frames, e);
+                    png_dir = None;
+                }
+            }
+            if let Some(out) = raw_stdout.as_mut() {
+                if let Err(e) = out.write_all(&frame).and_then(|_| out.flush()) {
+                    warn!("Failed to write a frame into stdout ({}), not writing any more", e);
+                    raw_stdout = None;
+                }
+            }
+            #[cfg(feature = "recording")]
+            if recording {
+                if let Err(e) = recorder.process_frame(frame) {
+                    warn!("Recording failed, continuing without it: {}", e);
+                }
+                recording = recorder.is_recording();
+            }
+        }
+
+        if let Some(budget) = frame_budget {
+            next_frame += budget;
+            let now = std::time::Instant::now();
+            if next_frame > now {
+                std::thread::sleep(next_frame - now);
+            } else {
+                // running behind, don't rush the next frames to catch up
+                next_frame = now;
+            }
+        }
+        frames_drawn += 1;
+    }
+    if canvas.window().fullscreen_state() != FullscreenType::Off {
+        let _ = canvas.window_mut().set_fullscreen(FullscreenType::Off);
+    }
+    if let Some(sound) = sound {
+        sound.close();
+    }
+    #[cfg(feature = "recording")]
+    if let Err(e) = recorder.done() {
+        warn!("The recording may be broken: {}", e);
     }
-        let mut a = ffmpeg.lock().unwrap();
-        a.done();
-}
\ No newline at end of file
+    let outputs = recording.then(|| std::path::PathBuf::from(&video)).into_iter().chain(png_dir).collect::<Vec<_>>();
+    finalize(&config, &sims, &overlays, csv, (grid_width, band_height), &outputs);
+}
+
+/// Starts FFMpeg recording into `video`, or a recorder that doesn't record when that's off or FFMpeg can't be started
+#[cfg(feature = "recording")]
+fn start_recording(config: &config::Config, video: &str) -> ffmpeg::VideoRecorder {
+    if !config.record {
+        return ffmpeg::VideoRecorder::disabled();
+    }
+    let mut encoder = config.encoder.clone();
+    if encoder.codec != ffmpeg::Encoder::default().codec && !ffmpeg::encoder_available(&encoder.codec) {
+        warn!("FFMpeg doesn't have the {} encoder, recording with libx264 instead", encoder.codec);
+        encoder.codec = ffmpeg::Encoder::default().codec;
+    }
+    ffmpeg::VideoRecorder::new(video, config.record_width, config.record_height, config.record_fps, &encoder).unwrap_or_else(|e| {
+        warn!("Failed to start FFMpeg ({}), continuing without recording", e);
+        ffmpeg::VideoRecorder::disabled()
+    })
+}
+
+/// Finishes every output of the run other than the video (finished right before), however the loop ended
+/// (closing the window, Ctrl-C or the hold running out). A panic skips this, but the recorder still finishes
+/// the video when it gets dropped
+fn finalize(
+    config: &config::Config,
+    sims: &[Simulation],
+    overlays: &[Simulation],
+    mut csv: Option<metrics::CsvWriter>,
+    (grid_width, band_height): (usize, usize),
+    outputs: &[std::path::PathBuf],
+) {
+    if let Some(Err(e)) = csv.as_mut().map(metrics::CsvWriter::flush) {
+        warn!("Failed to write metrics ({})", e);
+    }
+
+    for sim in sims {
+        info!(
+            "Growth factor {}: peak capacity {}, wasted slots over time {}, copy amplification {:.2}x, write overhead {:.0}%",
+            sim.array.growth,
+            sim.peak_capacity,
+            sim.wasted_slots,
+            sim.array.copy_amplification(),
+            sim.copies_per_append() * 100.0
+        );
+    }
+    if let Some(path) = &config.svg_out {
+        if let Err(e) = svg::write(path, sims, grid_width, band_height, config.grid_every, &config.theme) {
+            warn!("Failed to write {} ({})", path, e);
+        }
+    }
+    let summaries = sims.iter().chain(overlays).map(|sim| sim.summary(&config.palette)).collect::<Vec<_>>();
+    // a manifest next to every video or frame directory, so a folder of them says what made which
+    let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    for output in outputs {
+        let dir = output.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(std::path::Path::new("."));
+        let entry = stats::ManifestEntry {
+            file: output.file_name().map_or_else(|| output.display().to_string(), |name| name.to_string_lossy().into_owned()),
+            timestamp,
+            growths: sims.iter().map(|sim| sim.array.growth).collect(),
+            grid_width,
+            grid_height: band_height * sims.len(),
+            runs: summaries.iter().map(|summary| &summary.stats).collect(),
+        };
+        if let Err(e) = stats::append_manifest(dir, &entry) {
+            warn!("Failed to add {} to the manifest ({})", output.display(), e);
+        }
+    }
+    if config.raw_stdout && config.stats_out.is_none() {
+        info!("Not writing the statistics into stdout after the frames, use --stats-out to get them");
+        return;
+    }
+    if let Err(e) = stats::write_summaries(&summaries, config.stats_out.as_deref()) {
+        warn!("Failed to write statistics: {}", e);
+    }
+}
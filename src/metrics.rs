@@ -0,0 +1,199 @@
+/// Per-tick metrics export as CSV, and reading it back in for replays
+use std::io::Write;
+
+use crate::simulation::Simulation;
+
+/// Rows written between flushes, so an interrupted run still leaves most of its rows behind
+const FLUSH_EVERY: usize = 60;
+
+pub const HEADER: &str = "growth,tick,size,capacity,memory_efficiency,operations_per_append,resizes,copy_operations,old_data_size,old_data_appended";
+
+/// A row of the CSV, the state of one array after a tick
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Row {
+    pub growth: f64,
+    pub tick: u64,
+    pub size: usize,
+    pub capacity: usize,
+    pub memory_efficiency: f64,
+    pub operations_per_append: f64,
+    pub resizes: usize,
+    pub copy_operations: usize,
+    pub old_data_size: usize,
+    pub old_data_appended: usize,
+}
+
+/// Parses field `index` of a row, `line` is only for the error message
+fn field<T: std::str::FromStr>(fields: &[&str], index: usize, line: usize) -> Result<T, String> {
+    let column = HEADER.split(',').nth(index).unwrap();
+    fields[index].trim().parse().map_err(|_| format!("Line {}: invalid {}: {}", line, column, fields[index]))
+}
+
+/// Reads the rows of a CSV written by [`CsvWriter`]
+pub fn read_rows(path: &str) -> Result<Vec<Row>, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {} ({})", path, e))?;
+    let mut lines = contents.lines();
+    match lines.next() {
+        Some(header) if header.trim() == HEADER => {}
+        Some(header) => return Err(format!("{} doesn't look like a metrics CSV, expected the header\n{}\nbut got\n{}", path, HEADER, header)),
+        None => return Err(format!("{} is empty", path)),
+    }
+    let columns = HEADER.split(',').count();
+    let mut rows = vec![];
+    for (i, line) in lines.enumerate() {
+        let line_number = i + 2;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = line.split(',').collect::<Vec<_>>();
+        if fields.len() != columns {
+            return Err(format!("Line {}: expected {} columns but got {}", line_number, columns, fields.len()));
+        }
+        let row = Row {
+            growth: field(&fields, 0, line_number)?,
+            tick: field(&fields, 1, line_number)?,
+            size: field(&fields, 2, line_number)?,
+            capacity: field(&fields, 3, line_number)?,
+            memory_efficiency: field(&fields, 4, line_number)?,
+            operations_per_append: field(&fields, 5, line_number)?,
+            resizes: field(&fields, 6, line_number)?,
+            copy_operations: field(&fields, 7, line_number)?,
+            old_data_size: field(&fields, 8, line_number)?,
+            old_data_appended: field(&fields, 9, line_number)?,
+        };
+        if row.size > row.capacity || row.old_data_size > row.size || row.old_data_appended > row.old_data_size {
+            return Err(format!("Line {}: size, capacity and old data don't add up", line_number));
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+pub struct CsvWriter {
+    out: std::io::BufWriter<std::fs::File>,
+    rows: usize,
+}
+
+impl CsvWriter {
+    /// Creates (or truncates) the file at `path` and writes the header into it
+    pub fn new(path: &str) -> std::io::Result<Self> {
+        let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(out, "{}", HEADER)?;
+        Ok(Self { out, rows: 0 })
+    }
+
+    /// Writes the current state of `sim` as a row
+    pub fn write_row(&mut self, sim: &Simulation) -> std::io::Result<()> {
+        let array = &sim.array;
+        writeln!(
+            self.out,
+            "{},{},{},{},{},{},{},{},{},{}",
+            array.growth,
+            sim.ticks,
+            array.size,
+            array.capacity,
+            sim.memory_efficiency,
+            sim.operations_per_append,
+            array.resizes,
+            array.copy_operations,
+            array.old_data_size,
+            array.old_data_appended
+        )?;
+        self.rows += 1;
+        if self.rows.is_multiple_of(FLUSH_EVERY) {
+            self.out.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Writes every [`ResizeEvent`] into a file as a line of JSON
+pub struct EventLog {
+    out: std::io::BufWriter<std::fs::File>,
+    /// Events of each array written so far
+    written: Vec<usize>,
+}
+
+impl EventLog {
+    /// Creates (or truncates) the file at `path`
+    pub fn new(path: &str) -> std::io::Result<Self> {
+        Ok(Self { out: std::io::BufWriter::new(std::fs::File::create(path)?), written: vec![] })
+    }
+
+    /// Writes the resizes of `sims` since the last call
+    pub fn write_new(&mut self, sims: &[Simulation]) -> std::io::Result<()> {
+        self.written.resize(sims.len(), 0);
+        for (sim, written) in sims.iter().zip(&mut self.written) {
+            for event in &sim.resize_events[*written..] {
+                serde_json::to_writer(&mut self.out, event)?;
+                writeln!(self.out)?;
+            }
+            *written = sim.resize_events.len();
+        }
+        // resizes are rare enough to flush each time, so the log is complete even if the run gets killed
+        self.out.flush()
+    }
+
+    /// Starts over with arrays that haven't resized yet, their events get written from the first one
+    pub fn restart(&mut self) {
+        self.written.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::ResizeEvent;
+
+    fn read(contents: &str) -> Result<Vec<Row>, String> {
+        let path = std::env::temp_dir().join(format!("metrics-test-{}-{}.csv", std::process::id(), contents.len()));
+        std::fs::write(&path, contents).unwrap();
+        let rows = read_rows(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        rows
+    }
+
+    #[test]
+    fn rows_round_trip_and_bad_ones_are_rejected() {
+        let mut sim = Simulation::new(crate::array::Array::new(2.0, Some(50)));
+        let path = std::env::temp_dir().join(format!("metrics-test-{}.csv", std::process::id()));
+        let mut writer = CsvWriter::new(path.to_str().unwrap()).unwrap();
+        let mut expected = vec![];
+        for _ in 0..40 {
+            sim.tick();
+            writer.write_row(&sim).unwrap();
+            expected.push((sim.ticks, sim.array.size, sim.array.capacity, sim.array.old_data_size, sim.array.old_data_appended));
+        }
+        writer.flush().unwrap();
+        let rows = read_rows(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let got = rows.iter().map(|r| (r.tick, r.size, r.capacity, r.old_data_size, r.old_data_appended)).collect::<Vec<_>>();
+        assert_eq!(got, expected);
+
+        assert!(read("growth,tick\n2,1\n").unwrap_err().contains("header"));
+        assert!(read(&format!("{}\n2,1,1,1,1,1,0,0,0\n", HEADER)).unwrap_err().contains("Line 2"));
+        assert!(read(&format!("{}\n2,1,x,1,1,1,0,0,0,0\n", HEADER)).unwrap_err().contains("invalid size"));
+        assert!(read(&format!("{}\n2,1,5,1,1,1,0,0,0,0\n", HEADER)).unwrap_err().contains("don't add up"));
+    }
+
+    #[test]
+    fn every_resize_gets_logged_once() {
+        let mut sim = Simulation::new(crate::array::Array::new(2.0, Some(50)));
+        let path = std::env::temp_dir().join(format!("events-test-{}.jsonl", std::process::id()));
+        let mut log = EventLog::new(path.to_str().unwrap()).unwrap();
+        for _ in 0..40 {
+            sim.tick();
+            log.write_new(std::slice::from_ref(&sim)).unwrap();
+        }
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines = contents.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), sim.array.resizes);
+        assert_eq!(lines[3], r#"{"growth":2.0,"tick":9,"old_capacity":8,"new_capacity":16,"copied":8,"in_place":false}"#);
+        assert_eq!(sim.resize_events[3], ResizeEvent { growth: 2.0, tick: 9, old_capacity: 8, new_capacity: 16, copied: 8, in_place: false });
+    }
+}
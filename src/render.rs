@@ -0,0 +1,898 @@
+/// Drawing the memory map and the stat panel
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, TextureCreator};
+use sdl2::ttf::Font;
+use sdl2::video::{Window, WindowContext};
+
+use crate::array::Array;
+use crate::config::{Config, Metric};
+use crate::simulation::Simulation;
+use crate::stats;
+use crate::theme::{Palette, Theme};
+
+/// Where everything goes for a given window size. The number of cells never changes,
+/// only how big they're drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub cell_size: usize,
+    /// Area taken by the memory map
+    pub grid: Rect,
+    /// Left edge of everything right of the grid
+    pub panel_x: i32,
+    /// Area for the stat panel text, between the histogram and the graphs
+    pub panel: Rect,
+    /// Area of the efficiency graph
+    pub graph: Rect,
+    /// Area of the capacity and size graph, right next to the efficiency graph
+    pub size_graph: Rect,
+    /// Area of the resize size histogram, top right corner above the stat panel
+    pub histogram: Rect,
+    /// Area of the copies per tick sparkline, left of the histogram
+    pub sparkline: Rect,
+    /// Strip of the resize timeline, across the top of the stat panel
+    pub timeline: Rect,
+    /// Strip of the freed blocks under the stat panel, only there after [`Layout::with_freelist`]
+    pub freelist: Option<Rect>,
+    /// Strip showing the whole capacity of every array, only there after [`Layout::with_minimap`]
+    pub minimap: Option<Rect>,
+}
+
+impl Layout {
+    /// Fits a `grid_width` x `grid_height` grid into the left 5/8 of the window (1000px of the original 1600px)
+    /// with the stat panel and graph to the right of it. The grid only takes up whole cells, so with cell sizes
+    /// that don't divide the space evenly everything right of it starts where the last column ends
+    pub fn new(width: u32, height: u32, grid_width: usize, grid_height: usize) -> Self {
+        let grid_area_width = width as usize * 5 / 8;
+        let cell_size = (grid_area_width / grid_width).min(height as usize / grid_height).max(1);
+        let grid = Rect::new(0, 0, (grid_width * cell_size) as u32, (grid_height * cell_size) as u32);
+        let panel_x = grid.right();
+        let graphs_width = (width as i32 - panel_x - 40).max(2);
+        let graph = Rect::new(panel_x + 20, height as i32 - 200, (graphs_width / 2 - 10).max(1) as u32, 180);
+        let size_graph = Rect::new(graph.right() + 20, graph.y(), (graphs_width - graph.width() as i32 - 20).max(1) as u32, 180);
+        let histogram_width = (width as i32 - panel_x - 40).clamp(1, 220) as u32;
+        let histogram = Rect::new(width as i32 - histogram_width as i32 - 20, 30, histogram_width, 60);
+        let sparkline = Rect::new(panel_x + 20, histogram.y(), (histogram.x() - panel_x - 40).max(1) as u32, histogram.height());
+        // room for the histogram's axis labels and the timeline's own label above it
+        let timeline = Rect::new(panel_x + 20, histogram.bottom() + 50, (width as i32 - panel_x - 40).max(1) as u32, TIMELINE_HEIGHT);
+        let panel_top = timeline.bottom() + 10;
+        let panel = Rect::new(panel_x + 10, panel_top, (width as i32 - panel_x - 30).max(1) as u32, (graph.y() - 20 - panel_top).max(1) as u32);
+        Self {
+            cell_size,
+            grid,
+            panel_x,
+            panel,
+            graph,
+            size_graph,
+            histogram,
+            sparkline,
+            timeline,
+            freelist: None,
+            minimap: None,
+        }
+    }
+
+    /// Takes a strip `height` tall off the bottom of the stat panel, with room for a label above it
+    fn take_strip(&mut self, height: u32) -> Rect {
+        let strip = Rect::new(self.panel.x() + 10, self.panel.bottom() - height as i32, self.panel.width().saturating_sub(20).max(1), height);
+        self.panel.set_height(self.panel.height().saturating_sub(height + 30).max(1));
+        strip
+    }
+
+    /// Takes the bottom of the stat panel for the free list strip, with room for a label above it
+    pub fn with_freelist(mut self) -> Self {
+        self.freelist = Some(self.take_strip(FREELIST_HEIGHT));
+        self
+    }
+
+    /// Takes the bottom of the stat panel (above the free list) for a minimap row per array of `maps`
+    pub fn with_minimap(mut self, maps: usize) -> Self {
+        self.minimap = Some(self.take_strip(MINIMAP_PITCH * maps as u32));
+        self
+    }
+}
+
+/// What an allocated slot of the memory map holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellState {
+    /// Data appended since the last resize
+    Live,
+    /// Data from before the resize that hasn't been copied over yet
+    OldPending,
+    /// Data from before the resize that's being copied over
+    OldCopying,
+    /// Allocated but nothing in it yet
+    Empty,
+    /// Allocated by [`Array::reserve`] ahead of time and nothing in it yet
+    Reserved,
+    /// Past the capacity, not part of the array at all
+    Unallocated,
+}
+
+impl CellState {
+    /// Every state that gets drawn, [`CellState::Unallocated`] slots are left as background
+    pub const ALL: [CellState; 5] = [CellState::Live, CellState::OldPending, CellState::OldCopying, CellState::Empty, CellState::Reserved];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CellState::Live => "Live",
+            CellState::OldPending => "Old",
+            CellState::OldCopying => "Copying",
+            CellState::Empty => "Empty",
+            CellState::Reserved => "Reserved",
+            CellState::Unallocated => "Unallocated",
+        }
+    }
+}
+
+/// Color of `state`, [`None`] for [`CellState::Unallocated`] which isn't drawn
+pub fn state_color(state: CellState, palette: &Palette) -> Option<Color> {
+    match state {
+        CellState::Live => Some(palette.live),
+        CellState::OldPending => Some(palette.old_pending),
+        CellState::OldCopying => Some(palette.old_copying),
+        CellState::Empty => Some(palette.empty),
+        CellState::Reserved => Some(palette.reserved),
+        CellState::Unallocated => None,
+    }
+}
+
+/// State of slot `index` of `array`. The old data is `0..old_data_size`, of which `0..old_data_appended`
+/// has been copied over already, and everything appended since the resize is `old_data_size..size`.
+/// Once the limit is reached the copy is frozen, so nothing shows as copying anymore
+pub fn cell_state(array: &Array, index: usize, limited_reached: bool) -> CellState {
+    if index >= array.capacity {
+        CellState::Unallocated
+    } else if index >= array.size && index < array.reserved {
+        CellState::Reserved
+    } else if index >= array.size {
+        CellState::Empty
+    } else if index >= array.old_data_size {
+        CellState::Live
+    } else if index < array.old_data_appended && !limited_reached {
+        CellState::OldCopying
+    } else {
+        CellState::OldPending
+    }
+}
+
+/// Color of the cell showing slot `index` of `array`, [`None`] when the slot isn't allocated at all
+pub fn cell_color(array: &Array, index: usize, limited_reached: bool, palette: &Palette) -> Option<Color> {
+    state_color(cell_state(array, index, limited_reached), palette)
+}
+
+/// How far along its run slot `index` is, from 0 for the first element to 1 for the most recent one.
+/// Only live data and the old data copied so far have an order worth showing, the rest is always 1
+pub fn insertion_order(array: &Array, state: CellState, index: usize) -> f64 {
+    let (start, end) = match state {
+        CellState::Live => (array.old_data_size, array.size),
+        CellState::OldCopying => (0, array.old_data_appended),
+        _ => return 1.0,
+    };
+    if end - start <= 1 {
+        return 1.0;
+    }
+    (index - start) as f64 / (end - start - 1) as f64
+}
+
+/// `color` darkened to a third of its brightness at `t = 0`, unchanged at `t = 1`
+pub fn shade(color: Color, t: f64) -> Color {
+    let factor = 0.35 + 0.65 * t.clamp(0.0, 1.0);
+    let channel = |c: u8| (c as f64 * factor).round() as u8;
+    Color::RGB(channel(color.r), channel(color.g), channel(color.b))
+}
+
+/// [`cell_color`], shaded by insertion order when `gradient` is on
+fn map_color(array: &Array, index: usize, limited_reached: bool, palette: &Palette, gradient: bool) -> Option<Color> {
+    if !gradient {
+        return cell_color(array, index, limited_reached, palette);
+    }
+    let state = cell_state(array, index, limited_reached);
+    Some(shade(state_color(state, palette)?, insertion_order(array, state, index)))
+}
+
+/// `array` with only part of the slots it just grew by allocated, `t` of the way from `old_capacity` to its capacity,
+/// for sweeping a new block in with `--animate-resize`. Slots already holding something always show
+pub fn growing_into(array: &Array, old_capacity: usize, t: f64) -> Array {
+    let added = array.capacity.saturating_sub(old_capacity);
+    let mut shown = *array;
+    shown.capacity = (old_capacity + (added as f64 * t.clamp(0.0, 1.0)).round() as usize).clamp(array.size, array.capacity);
+    shown
+}
+
+/// Draws one cell per element slot of `array` into `area`, row by row.
+/// Slots that aren't allocated are left alone. With `gradient` the live and copied cells get darker
+/// the earlier they were put in
+pub fn draw_memory_map(canvas: &mut Canvas<Window>, array: &Array, limited_reached: bool, area: Rect, cell_size: usize, palette: &Palette, gradient: bool) -> Result<(), String> {
+    let grid_width = area.width() as usize / cell_size;
+    let grid_height = area.height() as usize / cell_size;
+    for x in 0..grid_width {
+        for y in 0..grid_height {
+            let index = x + y * grid_width;
+            if let Some(color) = map_color(array, index, limited_reached, palette, gradient) {
+                let rect = Rect::new(area.x() + x as i32 * cell_size as i32, area.y() + y as i32 * cell_size as i32, cell_size as u32, cell_size as u32);
+                canvas.set_draw_color(color);
+                canvas.fill_rect(rect)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Height of a row of the linear map, rows are this plus [`LINEAR_ROW_GAP`] apart
+const LINEAR_ROW_HEIGHT: u32 = 20;
+const LINEAR_ROW_GAP: u32 = 4;
+
+/// Splits the allocated slots of `array` into runs of the same [`CellState`], in order
+pub fn state_runs(array: &Array, limited_reached: bool) -> Vec<(std::ops::Range<usize>, CellState)> {
+    let mut bounds = vec![0, array.old_data_appended, array.old_data_size, array.size, array.reserved, array.capacity];
+    bounds.retain(|&b| b <= array.capacity);
+    bounds.sort_unstable();
+    bounds.dedup();
+    let mut runs: Vec<(std::ops::Range<usize>, CellState)> = vec![];
+    for pair in bounds.windows(2) {
+        let state = cell_state(array, pair[0], limited_reached);
+        match runs.last_mut() {
+            Some((range, last)) if *last == state => range.end = pair[1],
+            _ => runs.push((pair[0]..pair[1], state)),
+        }
+    }
+    runs
+}
+
+/// Draws the whole capacity of `array` as one long bar wrapped into thin rows filling `area`,
+/// so a slot's position along the bar is its index scaled to fit
+pub fn draw_linear_map(canvas: &mut Canvas<Window>, array: &Array, limited_reached: bool, area: Rect, palette: &Palette, gradient: bool) -> Result<(), String> {
+    if array.capacity == 0 {
+        return Ok(());
+    }
+    let pitch = LINEAR_ROW_HEIGHT + LINEAR_ROW_GAP;
+    let rows = (area.height() / pitch).max(1) as u64;
+    let width = area.width() as u64;
+    let length = rows * width;
+    for (range, state) in state_runs(array, limited_reached) {
+        let Some(color) = state_color(state, palette) else { continue };
+        canvas.set_draw_color(color);
+        let mut start = range.start as u64 * length / array.capacity as u64;
+        let end = range.end as u64 * length / array.capacity as u64;
+        let shaded = gradient && insertion_order(array, state, range.start) < 1.0;
+        while start < end {
+            let (row, column) = (start / width, start % width);
+            // a gradient changes color every pixel, a flat run gets drawn a row at a time
+            let span = if shaded { 1 } else { (end - start).min(width - column) };
+            if shaded {
+                let index = (start * array.capacity as u64 / length) as usize;
+                canvas.set_draw_color(shade(color, insertion_order(array, state, index.clamp(range.start, range.end - 1))));
+            }
+            canvas.fill_rect(Rect::new(area.x() + column as i32, area.y() + (row as u32 * pitch) as i32, span as u32, LINEAR_ROW_HEIGHT))?;
+            start += span;
+        }
+    }
+    Ok(())
+}
+
+/// Distance between the rows of the minimap, each is a single row of [`draw_linear_map`]
+const MINIMAP_PITCH: u32 = LINEAR_ROW_HEIGHT + LINEAR_ROW_GAP;
+
+/// Draws all of `array` into a row of `area` scaled to fit like [`draw_linear_map`], with an outline in `color`
+/// around the first `visible` slots, the part the grid has room for
+pub fn draw_minimap(canvas: &mut Canvas<Window>, array: &Array, limited_reached: bool, visible: usize, area: Rect, palette: &Palette, color: Color) -> Result<(), String> {
+    let row = Rect::new(area.x(), area.y(), area.width(), LINEAR_ROW_HEIGHT);
+    draw_linear_map(canvas, array, limited_reached, row, palette, false)?;
+    let shown = (visible.min(array.capacity) as u64 * row.width() as u64 / array.capacity.max(1) as u64).max(1);
+    canvas.set_draw_color(color);
+    canvas.draw_rect(Rect::new(row.x(), row.y(), shown as u32, row.height()))
+}
+
+/// Where the row of the `index`th array is in a minimap strip made by [`Layout::with_minimap`]
+pub fn minimap_row(strip: Rect, index: usize) -> Rect {
+    Rect::new(strip.x(), strip.y() + (index as u32 * MINIMAP_PITCH) as i32, strip.width(), MINIMAP_PITCH)
+}
+
+/// Gray of the blocks given up by earlier resizes in [`draw_history_map`]
+const ABANDONED_COLOR: Color = Color::RGB(120, 120, 120);
+/// Least distance between the rows of [`draw_history_map`], allocations past what fits are left out
+const HISTORY_MIN_PITCH: u32 = 5;
+
+/// Draws every allocation of `sim` as a bar scaled to its capacity, one row each with the oldest at the top.
+/// The blocks left behind by resizes are grayed out and the current one shows its slots like [`draw_linear_map`].
+/// When there are more allocations than rows fit, the oldest ones are left out
+pub fn draw_history_map(canvas: &mut Canvas<Window>, sim: &Simulation, area: Rect, palette: &Palette) -> Result<(), String> {
+    let mut blocks = sim.previous_allocations();
+    blocks.push(sim.array.capacity);
+    let largest = blocks.iter().copied().max().unwrap_or(0).max(1) as u64;
+    let pitch = (area.height() / blocks.len() as u32).clamp(HISTORY_MIN_PITCH, LINEAR_ROW_HEIGHT + LINEAR_ROW_GAP);
+    let shown = blocks.len().min((area.height() / pitch).max(1) as usize);
+    let row_height = (pitch * 4 / 5).max(1);
+    let width = area.width() as u64;
+    for (row, &capacity) in blocks[blocks.len() - shown..].iter().enumerate() {
+        let y = area.y() + (row as u32 * pitch) as i32;
+        let length = (capacity as u64 * width / largest).max(1);
+        if row + 1 < shown {
+            canvas.set_draw_color(ABANDONED_COLOR);
+            canvas.fill_rect(Rect::new(area.x(), y, length as u32, row_height))?;
+            continue;
+        }
+        for (range, state) in state_runs(&sim.array, sim.limited_reached) {
+            let start = range.start as u64 * length / capacity as u64;
+            let end = (range.end as u64 * length / capacity as u64).max(start + 1);
+            let Some(color) = state_color(state, palette) else { continue };
+            canvas.set_draw_color(color);
+            canvas.fill_rect(Rect::new(area.x() + start as i32, y, (end - start) as u32, row_height))?;
+        }
+    }
+    Ok(())
+}
+
+/// Height of the resize timeline strip, see [`Layout::timeline`]
+const TIMELINE_HEIGHT: u32 = 12;
+
+/// Draws a tick mark across `area` for every resize in `ticks`, placed by when it happened out of the `now` ticks so far.
+/// Geometric growth spaces them out further and further
+pub fn draw_timeline(canvas: &mut Canvas<Window>, ticks: impl Iterator<Item = u64>, now: u64, area: Rect, color: Color) -> Result<(), String> {
+    canvas.set_draw_color(color);
+    let width = area.width() as u64 - 1;
+    for tick in ticks {
+        let x = area.x() + (tick.min(now) * width / now.max(1)) as i32;
+        canvas.draw_line((x, area.y()), (x, area.bottom() - 1))?;
+    }
+    Ok(())
+}
+
+/// Height of the free list strip, see [`Layout::with_freelist`]
+const FREELIST_HEIGHT: u32 = 30;
+
+/// Draws `blocks` (capacities of freed allocations, oldest first) side by side across `area`,
+/// each as wide as its share of everything freed so far
+pub fn draw_freelist(canvas: &mut Canvas<Window>, blocks: &[usize], area: Rect) -> Result<(), String> {
+    let total = blocks.iter().sum::<usize>().max(1) as u64;
+    let width = area.width() as u64;
+    let mut freed = 0;
+    canvas.set_draw_color(ABANDONED_COLOR);
+    for &capacity in blocks {
+        let start = freed * width / total;
+        freed += capacity as u64;
+        // a pixel of gap so the blocks don't run together
+        let end = (freed * width / total).max(start + 2);
+        canvas.fill_rect(Rect::new(area.x() + start as i32, area.y(), (end - start - 1) as u32, area.height()))?;
+    }
+    Ok(())
+}
+
+/// Where slot `index` is in a map drawn by [`draw_memory_map`], [`None`] when it's past the visible grid
+fn grid_cell(index: usize, area: Rect, cell_size: usize) -> Option<Rect> {
+    let grid_width = area.width() as usize / cell_size;
+    if grid_width == 0 || index / grid_width >= area.height() as usize / cell_size {
+        return None;
+    }
+    let x = area.x() + ((index % grid_width) * cell_size) as i32;
+    let y = area.y() + ((index / grid_width) * cell_size) as i32;
+    Some(Rect::new(x, y, cell_size as u32, cell_size as u32))
+}
+
+/// Outlines the cell of slot `index` in a map drawn by [`draw_memory_map`]
+pub fn draw_grid_cursor(canvas: &mut Canvas<Window>, index: usize, area: Rect, cell_size: usize, color: Color) -> Result<(), String> {
+    let Some(cell) = grid_cell(index, area, cell_size) else { return Ok(()) };
+    canvas.set_draw_color(color);
+    canvas.draw_rect(cell)?;
+    canvas.draw_rect(Rect::new(cell.x() + 1, cell.y() + 1, cell.width().saturating_sub(2).max(1), cell.height().saturating_sub(2).max(1)))
+}
+
+/// Fills the cell of slot `index` in a map drawn by [`draw_memory_map`]
+pub fn fill_grid_cell(canvas: &mut Canvas<Window>, index: usize, area: Rect, cell_size: usize, color: Color) -> Result<(), String> {
+    let Some(cell) = grid_cell(index, area, cell_size) else { return Ok(()) };
+    canvas.set_draw_color(color);
+    canvas.fill_rect(cell)
+}
+
+/// Red fading into `empty` as `t` goes from 0 to 1, for slots that just got popped
+pub fn removed_color(empty: Color, t: f64) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    let channel = |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * t).round() as u8;
+    Color::RGB(channel(255, empty.r), channel(0, empty.g), channel(0, empty.b))
+}
+
+/// Marks slot `index` in a map drawn by [`draw_linear_map`] with a line across its row
+pub fn draw_linear_cursor(canvas: &mut Canvas<Window>, array: &Array, index: usize, area: Rect, color: Color) -> Result<(), String> {
+    if index >= array.capacity {
+        return Ok(());
+    }
+    let pitch = LINEAR_ROW_HEIGHT + LINEAR_ROW_GAP;
+    let rows = (area.height() / pitch).max(1) as u64;
+    let width = area.width() as u64;
+    let position = index as u64 * rows * width / array.capacity as u64;
+    let (row, column) = (position / width, position % width);
+    canvas.set_draw_color(color);
+    canvas.fill_rect(Rect::new(area.x() + column as i32, area.y() + (row as u32 * pitch) as i32 - 2, 2, LINEAR_ROW_HEIGHT + 4))
+}
+
+/// Cells out of `cells` that have a grid line in front of them, every `every`th one plus the far edge
+pub fn grid_lines(cells: usize, every: usize) -> impl Iterator<Item = usize> {
+    (0..=cells).filter(move |cell| cell.is_multiple_of(every) || *cell == cells)
+}
+
+/// Draws the lattice between the cells of `area`, a line every `every` cells plus the outer edges.
+/// Only whole cells are covered, a partial column or row at the edge of `area` doesn't get lines
+pub fn draw_grid_lines(canvas: &mut Canvas<Window>, area: Rect, cell_size: usize, every: usize, color: Color) -> Result<(), String> {
+    canvas.set_draw_color(color);
+    let grid_width = area.width() as usize / cell_size;
+    let grid_height = area.height() as usize / cell_size;
+    let (right, bottom) = (area.x() + (grid_width * cell_size) as i32, area.y() + (grid_height * cell_size) as i32);
+    for x in grid_lines(grid_width, every) {
+        let x_pos = area.x() + (x * cell_size) as i32;
+        canvas.draw_line((x_pos, area.y()), (x_pos, bottom))?;
+    }
+    for y in grid_lines(grid_height, every) {
+        let y_pos = area.y() + (y * cell_size) as i32;
+        canvas.draw_line((area.x(), y_pos), (right, y_pos))?;
+    }
+    Ok(())
+}
+
+/// Renders `text` with its top left corner at `x`, `y`. Returns the height of the rendered line
+pub fn draw_text(canvas: &mut Canvas<Window>, font: &Font, texture_creator: &TextureCreator<WindowContext>, text: &str, x: i32, y: i32, color: Color) -> Result<u32, String> {
+    let surface = font.render(text).blended(color).map_err(|e| e.to_string())?;
+    let texture = surface.as_texture(texture_creator).map_err(|e| e.to_string())?;
+    canvas.copy(&texture, None, Some(Rect::new(x, y, surface.width(), surface.height())))?;
+    Ok(surface.height())
+}
+
+/// Renders `lines` below each other starting at `x`, `y`. Returns the y right below the last line
+pub fn draw_lines(canvas: &mut Canvas<Window>, font: &Font, texture_creator: &TextureCreator<WindowContext>, lines: &[String], x: i32, mut y: i32, color: Color) -> Result<i32, String> {
+    for line in lines {
+        y += draw_text(canvas, font, texture_creator, line, x, y, color)? as i32;
+    }
+    Ok(y)
+}
+
+/// Lays `lines` out top to bottom inside `area` one line height apart, each in its own color, carrying on in another column
+/// once a column is full. Empty lines just leave a gap, lines that don't fit anywhere are left out
+pub fn draw_panel(canvas: &mut Canvas<Window>, font: &Font, texture_creator: &TextureCreator<WindowContext>, lines: &[(String, Color)], area: Rect) -> Result<(), String> {
+    let line_height = font.size_of("Ag").map_err(|e| e.to_string())?.1 as i32;
+    let (mut x, mut y) = (area.x(), area.y());
+    let mut column_width = 0;
+    for (line, color) in lines {
+        if y + line_height > area.bottom() {
+            x += column_width as i32 + 20;
+            y = area.y();
+            column_width = 0;
+        }
+        if !line.is_empty() {
+            let (width, _) = font.size_of(line).map_err(|e| e.to_string())?;
+            if x + width as i32 > area.right() {
+                return Ok(());
+            }
+            draw_text(canvas, font, texture_creator, line, x, y, *color)?;
+            column_width = column_width.max(width);
+        }
+        y += line_height;
+    }
+    Ok(())
+}
+
+/// Draws a swatch and a label for every [`CellState`] in a row starting at `x`, `y`
+pub fn draw_legend(canvas: &mut Canvas<Window>, font: &Font, texture_creator: &TextureCreator<WindowContext>, x: i32, y: i32, theme: &Theme) -> Result<(), String> {
+    let swatch = font.height() as u32;
+    let mut x = x;
+    for state in CellState::ALL {
+        let Some(color) = state_color(state, &theme.palette) else { continue };
+        canvas.set_draw_color(color);
+        canvas.fill_rect(Rect::new(x, y, swatch, swatch))?;
+        x += swatch as i32 + 6;
+        let (label_width, _) = font.size_of(state.label()).map_err(|e| e.to_string())?;
+        draw_text(canvas, font, texture_creator, state.label(), x, y, theme.text)?;
+        x += label_width as i32 + 20;
+    }
+    Ok(())
+}
+
+/// Formats `n` with a comma between every group of thousands
+pub fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Formats `duration` in the unit that keeps it readable, like `350 ns`, `12.3 ms` or `1.20 s`
+pub fn short_duration(duration: std::time::Duration) -> String {
+    let nanos = duration.as_nanos();
+    match nanos {
+        0..1_000 => format!("{} ns", nanos),
+        1_000..1_000_000 => format!("{:.1} µs", nanos as f64 / 1e3),
+        1_000_000..1_000_000_000 => format!("{:.1} ms", nanos as f64 / 1e6),
+        _ => format!("{:.2} s", duration.as_secs_f64()),
+    }
+}
+
+/// A text bar `width` characters wide filled up to `fraction`, like `[####------]`
+pub fn progress_bar(fraction: f64, width: usize) -> String {
+    let filled = ((fraction.clamp(0.0, 1.0) * width as f64).round() as usize).min(width);
+    format!("[{}{}]", "#".repeat(filled), "-".repeat(width - filled))
+}
+
+/// The text of the stat panel for `sim` in `color`, a line per metric that applies to it.
+/// The efficiency blinks red after a resize that left the array mostly empty
+pub fn panel_lines(sim: &Simulation, metrics: &[Metric], color: Color) -> Vec<(String, Color)> {
+    let flash = sim.low_efficiency_flash();
+    metrics
+        .iter()
+        .flat_map(|&metric| stat_line(sim, metric).into_iter().map(move |line| (line, if metric == Metric::Efficiency && flash { Color::RED } else { color })))
+        .collect()
+}
+
+/// The lines `metric` adds to the stat panel of `sim`, none when it doesn't apply like the shrinks without `--shrink-threshold`
+fn stat_line(sim: &Simulation, metric: Metric) -> Vec<String> {
+    let array = &sim.array;
+    let line = match metric {
+        Metric::Efficiency => format!("Instantaneous efficiency: {:.3}%", sim.memory_efficiency * 100.0),
+        Metric::OpsThisAppend => format!("Ops this append: {}", sim.operations_per_append),
+        Metric::Capacity => format!("Capacity: {}", array.capacity),
+        Metric::Size => format!("Size: {}", array.size),
+        Metric::Growth if sim.vec_backed() => "Growth policy: std::Vec".to_string(),
+        Metric::Growth => match sim.preset {
+            Some(preset) => format!("Growth factor: {} ({})", array.growth, preset),
+            None => format!("Growth factor: {}", array.growth),
+        },
+        Metric::AverageEfficiency => format!("Average efficiency: {:.3}%", stats::mean(&sim.all_efficiencies) * 100.0),
+        // the trough right after a resize
+        Metric::MinimumEfficiency => format!("Minimum efficiency: {:.3}%", sim.all_efficiencies.iter().copied().fold(sim.memory_efficiency, f64::min) * 100.0),
+        Metric::AverageOps => format!("Avg ops/append: {:.3}", stats::mean(&sim.all_appends)),
+        Metric::TotalOperations => format!("Total operations: {}", thousands(sim.total_operations as usize)),
+        Metric::CopyOperations => format!("Copy operations: {}", array.copy_operations),
+        Metric::Resizes => match sim.predicted_resizes() {
+            Some(predicted) => format!("Resizes: {} (log(size)/log(g) predicts {})", array.resizes, predicted),
+            None => format!("Resizes: {}", array.resizes),
+        },
+        Metric::CopyRate => format!("Copies per tick (last 60): {:.2}", sim.copy_rate()),
+        Metric::CopyProgress if sim.copying() => {
+            let progress = array.old_data_appended as f64 / array.old_data_size as f64;
+            format!("Copy progress: {:.1}% {}", progress * 100.0, progress_bar(progress, 10))
+        }
+        Metric::CopyProgress => "Copy progress: idle".to_string(),
+        Metric::CopiesPerResize => format!("Copy operations per resize: {:.3}", array.copy_operations as f64 / array.resizes as f64),
+        // std::Vec doubles, so it's headed for the same value as a growth factor of 2
+        Metric::CopiesPerAppend => match if sim.vec_backed() { Some(1.0) } else { array.theoretical_copies_per_append() } {
+            Some(theoretical) => format!("Copies per append: {:.3} (1/(g-1) = {:.3})", sim.copies_per_append(), theoretical),
+            None => format!("Copies per append: {:.3} (1/(g-1) = N/A)", sim.copies_per_append()),
+        },
+        Metric::WorstAppend => format!("Worst-case single append: {} ops", array.worst_case_append),
+        Metric::Ticks => format!("Ticks: {}", thousands(sim.elapsed().0 as usize)),
+        Metric::Elapsed => format!("Elapsed: {:.1}s ({:.0} appends/s)", sim.elapsed().1.as_secs_f64(), sim.appends_per_second()),
+        Metric::AllocatedBytes => format!("Allocated bytes: {}", thousands(array.allocated_bytes())),
+        Metric::UsedBytes => format!("Used bytes: {}", thousands(array.used_bytes())),
+        Metric::WastedBytes => format!("Wasted bytes: {}", thousands(array.allocated_bytes() - array.used_bytes())),
+        Metric::FreedBlocks => format!("Freed blocks: {} ({} bytes)", sim.previous_allocations().len(), thousands(sim.freed_bytes())),
+        Metric::NextResize => match sim.next_resize() {
+            Some((appends, Some(capacity))) => format!("Next resize in: {} appends (to {})", thousands(appends), thousands(capacity)),
+            Some((appends, None)) => format!("Next resize in: {} appends", thousands(appends)),
+            None => return vec![],
+        },
+        Metric::UntilLimit => match sim.remaining_to_limit() {
+            Some((appends, operations)) => format!("Until limit: {} appends (~{} ops)", thousands(appends), thousands(operations.round() as usize)),
+            None => return vec![],
+        },
+        Metric::CopyTime => match array.estimated_copy_time() {
+            Some(time) => format!("Estimated copy time: {}", short_duration(time)),
+            None => return vec![],
+        },
+        Metric::AppendsPerFrame if sim.appends_per_frame > 1 => format!("Appends per frame: {}", sim.appends_per_frame),
+        Metric::InPlace if sim.inplace_prob > 0.0 => {
+            return vec![
+                format!("In-place resizes: {}", array.inplace_resizes),
+                format!("Copying resizes: {}", array.resizes - array.inplace_resizes),
+                format!("Fragmentation: {:.1}%", sim.fragmentation() * 100.0),
+            ];
+        }
+        Metric::Shrinks if array.shrink_threshold.is_some() => format!("Shrinks: {}", array.shrinks),
+        Metric::AppendsPerFrame | Metric::InPlace | Metric::Shrinks => return vec![],
+    };
+    vec![line]
+}
+
+/// The text of the summary shown at the end of the run for `sim`
+pub fn summary_lines(sim: &Simulation) -> Vec<String> {
+    let mut lines = vec![
+        format!("Growth factor: {}", sim.array.growth),
+        format!("Peak capacity: {}", thousands(sim.peak_capacity)),
+        format!("Wasted slots over time: {}", thousands(sim.wasted_slots as usize)),
+        format!("Mean efficiency: {:.3}%", stats::mean(&sim.all_efficiencies) * 100.0),
+        format!("Copy amplification: {:.2}x", sim.array.copy_amplification()),
+        // writing each element once would be ideal, every copy writes it again
+        format!("Total writes: {} for {} appends", thousands(sim.total_writes()), thousands(sim.appends)),
+        format!("Write overhead: {:.0}%", sim.copies_per_append() * 100.0),
+    ];
+    if let Some(time) = sim.array.estimated_copy_time() {
+        lines.push(format!("Estimated copy time: {}", short_duration(time)));
+    }
+    lines
+}
+
+/// What the latest resize of `sim` did, for pausing on it with `--explain`
+pub fn explain_lines(sim: &Simulation) -> Vec<String> {
+    let mut lines = vec![];
+    match sim.resize_events.last() {
+        Some(event) if event.old_capacity > 0 => {
+            lines.push(format!("Capacity of {} exhausted", thousands(event.old_capacity)));
+            lines.push(format!(
+                "Allocating {} slots ({:.2}x)",
+                thousands(event.new_capacity),
+                event.new_capacity as f64 / event.old_capacity as f64
+            ));
+            if event.in_place || event.copied == 0 {
+                lines.push("The block grew in place, nothing to copy".to_string());
+            } else {
+                lines.push(format!("Copying {} elements into the new block", thousands(event.copied)));
+            }
+        }
+        _ => lines.push(format!("Nothing allocated yet, allocating {} slots", thousands(sim.array.capacity))),
+    }
+    lines.push(String::new());
+    lines.push("Press space to continue".to_string());
+    lines
+}
+
+/// What the intro card says about the run, so a recording tells what it shows on its own
+pub fn intro_lines(config: &Config) -> Vec<String> {
+    let days = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs() / 86400);
+    let (year, month, day) = civil_date(days as i64);
+    let mut lines = vec![match config.growth_preset {
+        Some(preset) if config.compare.is_empty() && config.overlay.is_empty() => format!("Growth factor: {} ({})", config.growth_label, preset),
+        _ => format!("Growth factor: {}", config.growth_label.replace("_vs_", " vs ")),
+    }];
+    if config.vec {
+        lines.push("Growth policy: std::Vec".to_string());
+    }
+    if let Some(path) = &config.replay {
+        lines.push(format!("Replay of {}", path));
+    }
+    lines.push(format!("Recorded {:04}-{:02}-{:02}", year, month, day));
+    lines
+}
+
+/// Year, month and day of `days` days after 1970-01-01 in the proleptic Gregorian calendar
+pub fn civil_date(days: i64) -> (i64, u32, u32) {
+    // Howard Hinnant's days_from_civil run backwards, with eras of 400 years starting in March
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Draws `title` and `lines` in a box centered on `area`, see-through enough to still show what's below it
+pub fn draw_overlay(canvas: &mut Canvas<Window>, font: &Font, texture_creator: &TextureCreator<WindowContext>, title: &str, lines: &[String], area: Rect, theme: &Theme) -> Result<(), String> {
+    let mut width = font.size_of(title).map_err(|e| e.to_string())?.0;
+    for line in lines {
+        width = width.max(font.size_of(line).map_err(|e| e.to_string())?.0);
+    }
+    let height = font.height() as u32 * (lines.len() as u32 + 1);
+    let padding = 20;
+    let rect = Rect::from_center(area.center(), width + 2 * padding, height + 2 * padding);
+    let background = theme.background;
+    canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(background.r, background.g, background.b, 220));
+    canvas.fill_rect(rect)?;
+    canvas.set_blend_mode(sdl2::render::BlendMode::None);
+    let x = rect.x() + padding as i32;
+    let y = rect.y() + padding as i32;
+    let y = y + draw_text(canvas, font, texture_creator, title, x, y, theme.text)? as i32;
+    draw_lines(canvas, font, texture_creator, lines, x, y, theme.text)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(array: &Array, limited_reached: bool, color: Color) -> usize {
+        (0..array.capacity + 10).filter(|&i| cell_color(array, i, limited_reached, &Palette::STANDARD) == Some(color)).count()
+    }
+
+    #[test]
+    fn colored_cells_match_size_and_capacity() {
+        let mut array = Array::new(2.0, None);
+        for _ in 0..100 {
+            if array.grow().is_err() && array.old_data_appended == array.old_data_size {
+                array.extend();
+                array.grow().unwrap();
+            }
+            let _ = array.append_old_data();
+
+            let green = count(&array, false, Color::GREEN);
+            let old = count(&array, false, Color::CYAN) + count(&array, false, Color::BLUE);
+            assert_eq!(green, array.size - array.old_data_size);
+            assert_eq!(green + old, array.size);
+            assert_eq!(green + old + count(&array, false, Color::BLACK), array.capacity);
+        }
+    }
+
+    #[test]
+    fn layout_matches_original_window() {
+        let layout = Layout::new(1600, 1000, 100, 100);
+        assert_eq!(layout.cell_size, 10);
+        assert_eq!(layout.grid, Rect::new(0, 0, 1000, 1000));
+        assert_eq!(layout.panel_x, 1000);
+        assert_eq!(layout.graph, Rect::new(1020, 800, 270, 180));
+        assert_eq!(layout.size_graph, Rect::new(1310, 800, 270, 180));
+        assert_eq!(layout.histogram, Rect::new(1360, 30, 220, 60));
+        assert_eq!(layout.sparkline, Rect::new(1020, 30, 320, 60));
+        assert_eq!(layout.timeline, Rect::new(1020, 140, 560, 12));
+        assert_eq!(layout.panel, Rect::new(1010, 162, 570, 618));
+    }
+
+    #[test]
+    fn freelist_sits_under_the_panel() {
+        let layout = Layout::new(1600, 1000, 100, 100).with_freelist();
+        let strip = layout.freelist.unwrap();
+        assert_eq!(strip, Rect::new(1020, 750, 550, 30));
+        assert!(layout.panel.bottom() < strip.y());
+        assert!(strip.bottom() < layout.graph.y());
+
+        let layout = Layout::new(1600, 1000, 100, 100).with_freelist().with_minimap(2);
+        let minimap = layout.minimap.unwrap();
+        assert_eq!(minimap.height(), 2 * MINIMAP_PITCH);
+        assert!(layout.panel.bottom() < minimap.y());
+        assert!(minimap.bottom() < layout.freelist.unwrap().y());
+        assert_eq!(minimap_row(minimap, 1).bottom(), minimap.bottom());
+    }
+
+    #[test]
+    fn grid_lines_align_with_cells_for_any_cell_size() {
+        for cell_size in [7, 10, 13] {
+            let cells = 1000 / cell_size;
+            let layout = Layout::new(1600, 1000, cells, cells);
+            assert_eq!(layout.cell_size, cell_size);
+            assert_eq!(layout.grid.width() as usize, cells * cell_size);
+            assert_eq!(layout.panel_x, layout.grid.right());
+            // the last line sits right after the last cell, and every cell starts on a line
+            let lines = grid_lines(cells, 1).map(|x| layout.grid.x() + (x * cell_size) as i32).collect::<Vec<_>>();
+            assert_eq!(*lines.last().unwrap(), layout.grid.right());
+            for index in [0, cells - 1, cells * cells - 1] {
+                let cell = grid_cell(index, layout.grid, cell_size).unwrap();
+                assert!(lines.contains(&cell.x()) && lines.contains(&cell.right()), "cell {} at {:?} for {}px", index, cell, cell_size);
+                assert!(cell.bottom() <= layout.grid.bottom());
+            }
+            assert_eq!(grid_cell(cells * cells, layout.grid, cell_size), None);
+        }
+        assert_eq!(grid_lines(10, 4).collect::<Vec<_>>(), vec![0, 4, 8, 10]);
+    }
+
+    #[test]
+    fn cell_states_follow_the_copy() {
+        let mut array = Array::new(2.0, None).with_initial_capacity(4);
+        for _ in 0..4 {
+            array.grow().unwrap();
+        }
+        array.extend();
+        array.grow().unwrap();
+        array.append_old_data().unwrap();
+        let states = (0..10).map(|i| cell_state(&array, i, false)).collect::<Vec<_>>();
+        use CellState::*;
+        assert_eq!(states, vec![OldCopying, OldPending, OldPending, OldPending, Live, Empty, Empty, Empty, Unallocated, Unallocated]);
+        // a frozen copy doesn't show as copying
+        assert_eq!(cell_state(&array, 0, true), OldPending);
+        assert_eq!(cell_color(&array, 8, false, &Palette::STANDARD), None);
+
+        array.copy_remaining();
+        array.reserve(6).unwrap();
+        let states = (4..12).map(|i| cell_state(&array, i, false)).collect::<Vec<_>>();
+        // the reserve copies the old block over like any resize
+        assert_eq!(states, vec![OldPending, Reserved, Reserved, Reserved, Reserved, Reserved, Reserved, Unallocated]);
+        assert_eq!(state_runs(&array, false).last(), Some(&(5..11, Reserved)));
+    }
+
+    #[test]
+    fn new_blocks_sweep_in() {
+        let mut array = Array::new(2.0, None);
+        while array.capacity < 8 {
+            array.step();
+        }
+        let before = 4;
+        assert_eq!(array.size, 5);
+        assert_eq!(growing_into(&array, before, 0.0).capacity, 5);
+        assert_eq!(growing_into(&array, before, 0.5).capacity, 6);
+        assert_eq!(growing_into(&array, before, 1.0), array);
+    }
+
+    #[test]
+    fn state_runs_match_cell_states() {
+        let mut array = Array::new(2.0, None);
+        for _ in 0..200 {
+            if array.grow().is_err() && array.old_data_appended == array.old_data_size {
+                array.extend();
+                array.grow().unwrap();
+            }
+            let _ = array.append_old_data();
+
+            let runs = state_runs(&array, false);
+            assert_eq!(runs.first().unwrap().0.start, 0);
+            assert_eq!(runs.last().unwrap().0.end, array.capacity);
+            for pair in runs.windows(2) {
+                assert_eq!(pair[0].0.end, pair[1].0.start);
+                assert_ne!(pair[0].1, pair[1].1);
+            }
+            for (range, state) in runs {
+                assert!(range.clone().all(|i| cell_state(&array, i, false) == state));
+            }
+        }
+    }
+
+    #[test]
+    fn gradient_runs_from_oldest_to_newest() {
+        let mut array = Array::new(2.0, None).with_initial_capacity(8);
+        for _ in 0..5 {
+            array.grow().unwrap();
+        }
+        assert_eq!(insertion_order(&array, CellState::Live, 0), 0.0);
+        assert_eq!(insertion_order(&array, CellState::Live, 4), 1.0);
+        assert_eq!(insertion_order(&array, CellState::Empty, 6), 1.0);
+        assert_eq!(shade(Color::RGB(200, 100, 0), 1.0), Color::RGB(200, 100, 0));
+        assert_eq!(shade(Color::RGB(200, 100, 0), 0.0), Color::RGB(70, 35, 0));
+    }
+
+    #[test]
+    fn durations_pick_a_readable_unit() {
+        let format = |nanos| short_duration(std::time::Duration::from_nanos(nanos));
+        assert_eq!(format(350), "350 ns");
+        assert_eq!(format(12_340), "12.3 µs");
+        assert_eq!(format(12_340_000), "12.3 ms");
+        assert_eq!(format(1_204_000_000), "1.20 s");
+    }
+
+    #[test]
+    fn progress_bar_fills_up() {
+        assert_eq!(progress_bar(0.0, 4), "[----]");
+        assert_eq!(progress_bar(0.5, 4), "[##--]");
+        assert_eq!(progress_bar(1.5, 4), "[####]");
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(59), (1970, 3, 1));
+        assert_eq!(civil_date(11_016), (2000, 2, 29));
+        assert_eq!(civil_date(20_740), (2026, 10, 14));
+        assert_eq!(civil_date(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn thousands_separators() {
+        assert_eq!(thousands(0), "0");
+        assert_eq!(thousands(999), "999");
+        assert_eq!(thousands(1000), "1,000");
+        assert_eq!(thousands(1234567), "1,234,567");
+    }
+
+    #[test]
+    fn explaining_an_in_place_resize_uses_its_own_capacities() {
+        let mut sim = Simulation::new(Array::new(2.0, None)).with_inplace_prob(1.0, 1);
+        while sim.resize_events.len() < 4 {
+            sim.tick();
+        }
+        let event = *sim.resize_events.last().unwrap();
+        assert!(event.in_place);
+        let lines = explain_lines(&sim);
+        assert_eq!(lines[0], format!("Capacity of {} exhausted", thousands(event.old_capacity)));
+        assert_eq!(lines[1], format!("Allocating {} slots (2.00x)", thousands(event.new_capacity)));
+        assert_eq!(lines[2], "The block grew in place, nothing to copy");
+    }
+
+    #[test]
+    fn empty_array_has_no_green_cells() {
+        let array = Array::new(2.0, None);
+        assert_eq!(count(&array, false, Color::GREEN), 0);
+        assert_eq!(cell_color(&array, 0, false, &Palette::STANDARD), Some(Color::BLACK));
+        assert_eq!(cell_color(&array, 1, false, &Palette::STANDARD), None);
+    }
+}
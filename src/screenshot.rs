@@ -0,0 +1,52 @@
+/// Saving frames as PNG and scaling them to the recording size
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Writes an RGB24 frame as a PNG named after the current time into the working directory.
+/// Returns the name of the file
+pub fn save(pixels: &[u8], width: u32, height: u32) -> Result<String, String> {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| e.to_string())?.as_millis();
+    let path = format!("screenshot-{}.png", millis);
+    image::save_buffer(&path, pixels, width, height, image::ColorType::Rgb8).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Writes an RGB24 frame as `frame_<number>.png` into `dir`, numbered with six digits so the files sort in order
+pub fn save_frame(dir: &Path, number: u64, pixels: &[u8], width: u32, height: u32) -> Result<(), String> {
+    let path = dir.join(format!("frame_{:06}.png", number));
+    image::save_buffer(path, pixels, width, height, image::ColorType::Rgb8).map_err(|e| e.to_string())
+}
+
+/// Scales an RGB24 frame of `width` x `height` to fit `target_width` x `target_height`,
+/// keeping the aspect ratio and filling the rest with black
+pub fn fit_frame(frame: Vec<u8>, width: u32, height: u32, target_width: u32, target_height: u32) -> Vec<u8> {
+    if (width, height) == (target_width, target_height) {
+        return frame;
+    }
+    let image = image::RgbImage::from_raw(width, height, frame).expect("Frame doesn't match its size");
+    let scale = (target_width as f64 / width as f64).min(target_height as f64 / height as f64);
+    let scaled_width = ((width as f64 * scale).round() as u32).clamp(1, target_width);
+    let scaled_height = ((height as f64 * scale).round() as u32).clamp(1, target_height);
+    let scaled = image::imageops::resize(&image, scaled_width, scaled_height, image::imageops::FilterType::Triangle);
+    let mut fitted = image::RgbImage::new(target_width, target_height);
+    image::imageops::overlay(&mut fitted, &scaled, ((target_width - scaled_width) / 2) as i64, ((target_height - scaled_height) / 2) as i64);
+    fitted.into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_get_letterboxed() {
+        let white = vec![255; 4 * 2 * 3];
+        assert_eq!(fit_frame(white.clone(), 4, 2, 4, 2), white);
+        // twice as wide as tall into a square, so the top and bottom quarter stay black
+        let fitted = fit_frame(white, 4, 2, 8, 8);
+        assert_eq!(fitted.len(), 8 * 8 * 3);
+        let row = |y: usize| &fitted[y * 8 * 3..(y + 1) * 8 * 3];
+        assert!(row(0).iter().all(|&c| c == 0));
+        assert!(row(4).iter().all(|&c| c == 255));
+        assert!(row(7).iter().all(|&c| c == 0));
+    }
+}
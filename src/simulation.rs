@@ -0,0 +1,1182 @@
+/// Bookkeeping around an [`Array`] that gets one element appended every tick
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::array::{Append, Array};
+use crate::{metrics, stats};
+
+/// Ticks making up a second on the logical clock, same as the recording's frame rate
+pub const TICKS_PER_SECOND: u64 = 60;
+
+/// Ticks [`Simulation::recent_copies`] keeps around for the sparkline
+pub const RECENT_TICKS: usize = 120;
+
+/// Ticks the copy rate is averaged over
+const COPY_RATE_TICKS: usize = 60;
+
+/// How long the final state is held before the run counts as finished by default, in seconds
+pub const HOLD_SECONDS: f64 = 3.0;
+
+/// Efficiency a resize settling below gets warned about, like it does with a huge growth factor
+pub const LOW_EFFICIENCY: f64 = 0.1;
+/// Ticks the efficiency stat flashes for after such a resize
+const LOW_EFFICIENCY_FLASH_TICKS: u64 = 60;
+
+/// Ticks a popped slot takes to fade from red back to empty
+pub const REMOVED_FADE_TICKS: u64 = 20;
+
+/// What a step of a `--script` does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Append,
+    Pop,
+    /// Nothing for a tick, the copy after a resize still goes on
+    Wait,
+}
+
+/// Parses a script like `append 100, pop 30, wait 60, append 200` into the operations and how many times to do each.
+/// The operations can be on lines of their own too, like in a `--step-script` file, where `#` starts a comment line
+pub fn parse_script(script: &str) -> Result<Vec<(Operation, usize)>, String> {
+    script
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|part| !part.is_empty() && !part.starts_with('#'))
+        .map(|part| {
+            let mut words = part.split_whitespace();
+            let operation = match words.next() {
+                Some("append" | "push") => Operation::Append,
+                Some("pop") => Operation::Pop,
+                Some("wait") => Operation::Wait,
+                _ => return Err(format!("Unknown script operation: {} (expected append <n>, pop <n> or wait <ticks>)", part)),
+            };
+            let count = words.next().and_then(|n| n.parse::<usize>().ok()).filter(|_| words.next().is_none());
+            count.map(|count| (operation, count)).ok_or_else(|| format!("Invalid script operation: {} (expected append <n>, pop <n> or wait <ticks>)", part))
+        })
+        .collect()
+}
+
+/// Capacity the next resize of `array` grows into, the growth factor's (or `needed` when that's more)
+/// rounded up to the next of `tiers` and capped at the hard limit
+fn tiered_capacity(array: &Array, tiers: &[usize], needed: usize) -> usize {
+    let capacity = array.next_capacity().max(needed);
+    let tier = tiers.iter().copied().find(|&tier| tier >= capacity).unwrap_or(capacity);
+    array.hard_limit.map_or(tier, |limit| tier.min(limit))
+}
+
+/// Draws from a Poisson distribution with a mean of `mean`. Knuth's method underflows for big means,
+/// so those get split into several smaller draws that add up to the same distribution
+fn poisson(rng: &mut impl Rng, mut mean: f64) -> usize {
+    let mut count = 0;
+    while mean > 0.0 {
+        let chunk = mean.min(30.0);
+        mean -= chunk;
+        let limit = (-chunk).exp();
+        let mut product = rng.gen::<f64>();
+        while product > limit {
+            count += 1;
+            product *= rng.gen::<f64>();
+        }
+    }
+    count
+}
+
+/// A resize, as written by `--log-events`
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct ResizeEvent {
+    pub growth: f64,
+    pub tick: u64,
+    pub old_capacity: usize,
+    pub new_capacity: usize,
+    /// Elements the resize has to copy over, 0 when it grew in place
+    pub copied: usize,
+    /// Grew the block where it was, so the old one wasn't given up
+    pub in_place: bool,
+}
+
+pub struct Simulation {
+    pub array: Array,
+    /// Memory efficiency after the last tick, see [`Array::memory_efficiency`]
+    pub memory_efficiency: f64,
+    /// Operations done by the last append (together with the copying after it), not an average
+    pub operations_per_append: f64,
+    /// Operations done so far, every append, resize and copy
+    pub total_operations: u64,
+    pub all_efficiencies: Vec<f64>,
+    pub all_appends: Vec<f64>,
+    /// Capacity and size after every append, for plotting them against each other
+    pub all_capacities: Vec<f64>,
+    pub all_sizes: Vec<f64>,
+    /// Capacity after every resize
+    pub resize_sizes: Vec<usize>,
+    /// Every resize so far, oldest first
+    pub resize_events: Vec<ResizeEvent>,
+    /// Capacity before the first resize
+    pub initial_capacity: usize,
+    /// Old elements copied in each of the last [`RECENT_TICKS`] ticks, oldest first
+    pub recent_copies: std::collections::VecDeque<usize>,
+    /// Slot the newest element got written into and the slot the last old element got copied into
+    /// during the last tick, [`None`] when nothing was written
+    pub last_write: Option<usize>,
+    pub last_copy: Option<usize>,
+    /// Slots popped in the last [`REMOVED_FADE_TICKS`] ticks and the tick they were popped at, oldest first
+    pub removed: std::collections::VecDeque<(usize, u64)>,
+    /// Growth factors to switch to once the size reaches each of the sizes, see [`Simulation::with_growth_schedule`]
+    pub growth_schedule: Vec<(usize, f64)>,
+    /// Size classes resizes round up to, sorted. Past the last one the growth factor is used as is
+    pub tiers: Vec<usize>,
+    /// Growth factor from before the first breakpoint of the schedule
+    base_growth: f64,
+    /// Operations still to do and how many times, the run ends once they're done. [`None`] just appends
+    pub script: Option<std::collections::VecDeque<(Operation, usize)>>,
+    /// Tick of the last resize that left the array less than [`LOW_EFFICIENCY`] full
+    low_efficiency_tick: Option<u64>,
+    /// Elements appended so far, popping doesn't take any back
+    pub appends: usize,
+    /// Biggest capacity reached
+    pub peak_capacity: usize,
+    /// Slots allocated but not holding live data, summed over every append until the limit
+    pub wasted_slots: u64,
+    /// Elements every resize and shrink so far moved into its new block, less what a shrink cut short,
+    /// which is what the copies and the rest of the current copy have to add up to
+    copies_owed: usize,
+    /// Set once the array can't grow anymore (or filled the visible grid when it has no limit),
+    /// nothing gets appended after that
+    pub limited_reached: bool,
+    pub last_limit_reached: std::time::Instant,
+    /// Ticks done so far
+    pub ticks: u64,
+    /// Tick [`Simulation::last_limit_reached`] was taken at
+    limit_tick: u64,
+    started: std::time::Instant,
+    /// Ticks and time it took to first reach the limit, see [`Simulation::elapsed`]
+    finished_after: Option<(u64, std::time::Duration)>,
+    /// Time the hold at the end with ticks instead of the wall clock, so the number of frames
+    /// (and with that the recording) is the same on every run
+    pub deterministic: bool,
+    /// Cells the array is shown in, an array without a hard limit stops once it fills them
+    pub visible_cells: usize,
+    /// Size to stop appending at, the visible grid doesn't matter anymore when set
+    pub target: Option<usize>,
+    /// How long the final state is held, in seconds
+    pub hold_seconds: f64,
+    /// Old elements copied per tick. When set, appends also wait until the copy is done,
+    /// otherwise one element gets copied per tick alongside the appends
+    pub copies_per_frame: Option<usize>,
+    /// Appends attempted per tick, each one still gets its own resize and copy handling
+    pub appends_per_frame: usize,
+    /// Elements every append puts in at once through [`Array::grow_by_with`], like `extend_from_slice`
+    pub bulk: Option<usize>,
+    /// Sizes at which to [`Array::reserve`] room for that many more elements, sorted by size. Done ones get taken off
+    reserve_at: Vec<(usize, usize)>,
+    /// Chance of a resize happening in place without copying anything
+    pub inplace_prob: f64,
+    /// Name of the `--growth-preset` the growth factor is from, shown next to it
+    pub preset: Option<&'static str>,
+    /// Mean appends per tick when they arrive at random, the steps of a tick are then Poisson distributed
+    /// around `insert_rate * appends_per_frame` instead of always being `appends_per_frame`
+    pub insert_rate: Option<f64>,
+    pub seed: u64,
+    rng: ChaCha8Rng,
+    /// Separate from `rng` so random arrivals don't change which resizes happen in place
+    arrival_rng: ChaCha8Rng,
+    /// A real `Vec` getting the same pushes, its reallocations decide the capacity instead of the growth factor
+    vec: Option<Vec<u8>>,
+}
+
+impl Simulation {
+    pub fn new(array: Array) -> Self {
+        Self {
+            memory_efficiency: array.memory_efficiency(),
+            array,
+            operations_per_append: 0.0,
+            total_operations: 0,
+            all_efficiencies: vec![],
+            all_appends: vec![],
+            all_capacities: vec![],
+            all_sizes: vec![],
+            resize_sizes: vec![],
+            resize_events: vec![],
+            initial_capacity: array.capacity,
+            recent_copies: std::collections::VecDeque::with_capacity(RECENT_TICKS),
+            last_write: None,
+            last_copy: None,
+            removed: std::collections::VecDeque::new(),
+            script: None,
+            growth_schedule: vec![],
+            tiers: vec![],
+            base_growth: array.growth,
+            low_efficiency_tick: None,
+            appends: 0,
+            peak_capacity: array.capacity,
+            wasted_slots: 0,
+            copies_owed: 0,
+            limited_reached: false,
+            last_limit_reached: std::time::Instant::now(),
+            ticks: 0,
+            limit_tick: 0,
+            started: std::time::Instant::now(),
+            finished_after: None,
+            deterministic: false,
+            visible_cells: usize::MAX,
+            target: None,
+            hold_seconds: HOLD_SECONDS,
+            copies_per_frame: None,
+            appends_per_frame: 1,
+            inplace_prob: 0.0,
+            bulk: None,
+            reserve_at: vec![],
+            preset: None,
+            insert_rate: None,
+            seed: 0,
+            rng: ChaCha8Rng::seed_from_u64(0),
+            arrival_rng: ChaCha8Rng::seed_from_u64(0),
+            vec: None,
+        }
+    }
+
+    /// Lets resizes happen in place with a chance of `inplace_prob`, decided by an RNG seeded with `seed`
+    pub fn with_inplace_prob(mut self, inplace_prob: f64, seed: u64) -> Self {
+        self.inplace_prob = inplace_prob;
+        self.seed = seed;
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+        self
+    }
+
+    /// Makes the appends arrive at random at a mean of `insert_rate` per tick, drawn from an RNG seeded with `seed`
+    pub fn with_insert_rate(mut self, insert_rate: Option<f64>, seed: u64) -> Self {
+        self.insert_rate = insert_rate;
+        self.arrival_rng = ChaCha8Rng::seed_from_u64(seed);
+        self.arrival_rng.set_stream(1);
+        self
+    }
+
+    /// Follows the reallocations of a real `Vec<u8>` instead of growing by the growth factor,
+    /// starting out empty like `Vec::new`
+    pub fn with_vec_backend(mut self) -> Self {
+        let vec = Vec::new();
+        self.array.capacity = vec.capacity();
+        self.initial_capacity = vec.capacity();
+        self.memory_efficiency = self.array.memory_efficiency();
+        self.vec = Some(vec);
+        self
+    }
+
+    /// Whether the capacity comes from a real `Vec`, see [`Simulation::with_vec_backend`]
+    pub fn vec_backed(&self) -> bool {
+        self.vec.is_some()
+    }
+
+    /// Grows by the factor of the last breakpoint in `schedule` (sorted by size) the size has reached,
+    /// like allocators that grow less aggressively once an array gets big
+    pub fn with_growth_schedule(mut self, schedule: Vec<(usize, f64)>) -> Self {
+        self.growth_schedule = schedule;
+        self.base_growth = self.array.growth;
+        self.apply_growth_schedule();
+        self
+    }
+
+    fn apply_growth_schedule(&mut self) {
+        let size = self.array.size;
+        self.array.growth = self.growth_schedule.iter().rev().find(|&&(from, _)| size >= from).map_or(self.base_growth, |&(_, growth)| growth);
+    }
+
+    /// Rounds every resize up to the smallest of `tiers` that fits what the growth factor asks for,
+    /// like allocators handing out fixed size classes
+    pub fn with_tiers(mut self, mut tiers: Vec<usize>) -> Self {
+        tiers.sort_unstable();
+        tiers.dedup();
+        self.tiers = tiers;
+        self
+    }
+
+    /// Follows `script` instead of appending until the limit, see [`parse_script`]
+    pub fn with_script(mut self, script: Option<Vec<(Operation, usize)>>) -> Self {
+        self.script = script.map(|script| script.into_iter().filter(|&(_, count)| count > 0).collect());
+        self
+    }
+
+    pub fn with_target(mut self, target: Option<usize>) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn with_reserve_at(mut self, reserve_at: Vec<(usize, usize)>) -> Self {
+        self.reserve_at = reserve_at;
+        self
+    }
+
+    /// Whether the array grew to the size of the next reserve
+    fn reserve_due(&self) -> bool {
+        self.reserve_at.first().is_some_and(|&(size, _)| self.array.size >= size)
+    }
+
+    /// Does the next reserve, returns the operations that took
+    fn reserve(&mut self) -> usize {
+        let (_, additional) = self.reserve_at.remove(0);
+        let (old_capacity, copies) = (self.array.capacity, self.array.copy_operations);
+        match self.array.reserve(additional) {
+            Ok(true) => {
+                info!("Reserved room for {} more elements, new capacity: {}", additional, self.array.capacity);
+                self.record_resize(old_capacity, false);
+                self.check_efficiency();
+                1 + self.array.copy_operations - copies
+            }
+            Ok(false) => 0,
+            Err(e) => {
+                warn!("Couldn't reserve room for {} more elements ({})", additional, e);
+                0
+            }
+        }
+    }
+
+    pub fn with_bulk(mut self, bulk: Option<usize>) -> Self {
+        self.bulk = bulk;
+        self
+    }
+
+    pub fn with_preset(mut self, preset: Option<&'static str>) -> Self {
+        self.preset = preset;
+        self
+    }
+
+    pub fn with_hold_seconds(mut self, hold_seconds: f64) -> Self {
+        self.hold_seconds = hold_seconds;
+        self
+    }
+
+    pub fn with_visible_cells(mut self, visible_cells: usize) -> Self {
+        self.visible_cells = visible_cells;
+        self
+    }
+
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Restarts the hold at the end
+    fn mark_limit_reached(&mut self) {
+        self.last_limit_reached = std::time::Instant::now();
+        self.limit_tick = self.ticks;
+        if self.finished_after.is_none() {
+            self.finished_after = Some((self.ticks, self.run_time()));
+        }
+    }
+
+    fn run_time(&self) -> std::time::Duration {
+        if self.deterministic {
+            std::time::Duration::from_secs_f64(self.ticks as f64 / TICKS_PER_SECOND as f64)
+        } else {
+            self.started.elapsed()
+        }
+    }
+
+    /// [`Array::check_invariants`], and that the copies done and still to do match what the resizes had to copy.
+    /// Only holds for simulated runs, a replay takes the counters from the CSV
+    pub fn check_invariants(&self) -> Result<(), String> {
+        self.array.check_invariants()?;
+        let pending = self.array.old_data_size - self.array.old_data_appended;
+        if self.array.copy_operations + pending != self.copies_owed {
+            return Err(format!("{} copies done and {} to go, but the resizes so far had {} to copy", self.array.copy_operations, pending, self.copies_owed));
+        }
+        Ok(())
+    }
+
+    /// Ticks every resize happened at, oldest first
+    pub fn resize_ticks(&self) -> impl Iterator<Item = u64> + '_ {
+        self.resize_events.iter().map(|event| event.tick)
+    }
+
+    /// Ticks and time the run has taken, both stop once the limit is reached.
+    /// The time is wall-clock, or counted in ticks when [`Simulation::deterministic`] so it's the same every run
+    pub fn elapsed(&self) -> (u64, std::time::Duration) {
+        self.finished_after.unwrap_or_else(|| (self.ticks, self.run_time()))
+    }
+
+    /// Appends per second of [`Simulation::elapsed`]
+    pub fn appends_per_second(&self) -> f64 {
+        let seconds = self.elapsed().1.as_secs_f64();
+        if seconds == 0.0 {
+            return 0.0;
+        }
+        self.appends as f64 / seconds
+    }
+
+    pub fn with_copies_per_frame(mut self, copies_per_frame: Option<usize>) -> Self {
+        self.copies_per_frame = copies_per_frame;
+        self
+    }
+
+    pub fn with_appends_per_frame(mut self, appends_per_frame: usize) -> Self {
+        self.appends_per_frame = appends_per_frame.max(1);
+        self
+    }
+
+    /// Whether old data is still being copied into the new allocation
+    pub fn copying(&self) -> bool {
+        self.array.old_data_appended < self.array.old_data_size
+    }
+
+    /// With shrinking on, the array gets emptied one element at a time once it's full.
+    /// A script decides for itself when to pop, so it doesn't get drained after it's done
+    pub fn draining(&self) -> bool {
+        self.limited_reached && self.array.shrink_threshold.is_some() && self.script.is_none()
+    }
+
+    /// Whether the script is at a pop
+    fn script_pops(&self) -> bool {
+        matches!(self.script.as_ref().and_then(|script| script.front()), Some((Operation::Pop, _)))
+    }
+
+    /// Whether the script is at a wait, see [`Operation::Wait`]
+    fn script_waits(&self) -> bool {
+        matches!(self.script.as_ref().and_then(|script| script.front()), Some((Operation::Wait, _)))
+    }
+
+    /// Counts one operation of the script as done, the run is over once there aren't any left
+    fn advance_script(&mut self) {
+        let Some(script) = self.script.as_mut() else { return };
+        if let Some((_, count)) = script.front_mut() {
+            *count -= 1;
+            if *count == 0 {
+                script.pop_front();
+            }
+        }
+        if script.is_empty() {
+            self.limited_reached = true;
+            self.mark_limit_reached();
+        }
+    }
+
+    /// Warns when the resize that just happened left the array mostly empty, even once the copy is done
+    fn check_efficiency(&mut self) {
+        let settled = self.array.size as f64 / self.array.capacity as f64;
+        if settled < LOW_EFFICIENCY {
+            warn!(
+                "Growth factor {} resized to {} slots for {} elements, only {:.1}% of it will be used",
+                self.array.growth,
+                self.array.capacity,
+                self.array.size,
+                settled * 100.0
+            );
+            self.low_efficiency_tick = Some(self.ticks);
+        }
+    }
+
+    /// Whether the efficiency stat should be drawn highlighted right now, it blinks for a second after
+    /// a resize that was warned about
+    pub fn low_efficiency_flash(&self) -> bool {
+        self.low_efficiency_tick.is_some_and(|tick| self.ticks - tick < LOW_EFFICIENCY_FLASH_TICKS && ((self.ticks - tick) / 10).is_multiple_of(2))
+    }
+
+    /// Pops the last element, remembering its slot so it can fade out
+    fn pop(&mut self) -> bool {
+        let (shrinks, pending) = (self.array.shrinks, self.array.old_data_size.saturating_sub(self.array.old_data_appended));
+        let Some(size) = self.array.pop() else { return false };
+        if self.array.shrinks > shrinks {
+            // a shrink in the middle of a copy starts over with what's left
+            self.copies_owed = self.copies_owed - pending + self.array.old_data_size;
+        }
+        if let Some(vec) = self.vec.as_mut() {
+            vec.pop();
+        }
+        self.removed.push_back((size, self.ticks));
+        progress!("Popped data, size: {} capacity: {}", self.array.size, self.array.capacity);
+        true
+    }
+
+    /// Slots popped recently that are still allocated and empty, with how far they've faded from 0 to 1
+    pub fn recently_removed(&self) -> impl Iterator<Item = (usize, f64)> + '_ {
+        self.removed
+            .iter()
+            .filter(|&&(index, _)| index >= self.array.size && index < self.array.capacity)
+            .map(|&(index, tick)| (index, (self.ticks - tick) as f64 / REMOVED_FADE_TICKS as f64))
+    }
+
+    /// Does [`Simulation::appends_per_frame`] steps, or a random number of them with [`Simulation::insert_rate`].
+    /// The operations of every append are recorded on their own, so batching doesn't change the per-append statistics
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+        self.last_write = None;
+        self.last_copy = None;
+        while self.removed.front().is_some_and(|&(_, tick)| tick + REMOVED_FADE_TICKS <= self.ticks) {
+            self.removed.pop_front();
+        }
+        let copies_before = self.array.copy_operations;
+        let steps = match self.insert_rate {
+            Some(rate) => poisson(&mut self.arrival_rng, rate * self.appends_per_frame as f64),
+            None => self.appends_per_frame,
+        };
+        // a wait takes whole ticks, no matter how many steps they have
+        let waiting = self.script_waits() && !self.limited_reached;
+        for _ in 0..steps {
+            self.step();
+        }
+        if waiting {
+            self.advance_script();
+        }
+        self.record_copies(self.array.copy_operations - copies_before);
+    }
+
+    /// Notes down the resize that just happened out of a block of `old_capacity`
+    fn record_resize(&mut self, old_capacity: usize, in_place: bool) {
+        self.resize_sizes.push(self.array.capacity);
+        self.copies_owed += self.array.old_data_size;
+        self.resize_events.push(ResizeEvent {
+            growth: self.array.growth,
+            tick: self.ticks,
+            old_capacity,
+            new_capacity: self.array.capacity,
+            copied: self.array.old_data_size,
+            in_place,
+        });
+    }
+
+    fn record_copies(&mut self, copies: usize) {
+        if self.recent_copies.len() == RECENT_TICKS {
+            self.recent_copies.pop_front();
+        }
+        self.recent_copies.push_back(copies);
+    }
+
+    /// Copies per tick averaged over the last [`COPY_RATE_TICKS`] ticks, spikes right after a resize
+    pub fn copy_rate(&self) -> f64 {
+        let window = self.recent_copies.len().min(COPY_RATE_TICKS);
+        if window == 0 {
+            return 0.0;
+        }
+        self.recent_copies.iter().rev().take(window).sum::<usize>() as f64 / window as f64
+    }
+
+    /// Appends one element or a chunk of [`Simulation::bulk`] (resizing if needed) or pops one when the script says so,
+    /// and copies old data over, see [`Simulation::copies_per_frame`].
+    /// A resize only happens once the previous copy is done, until then the step just copies.
+    /// Once the limit is reached the array is frozen, only finishing the copy that's still going on
+    /// (or getting popped empty when draining)
+    fn step(&mut self) {
+        let mut operations = 0;
+        let blocked = self.copies_per_frame.is_some() && self.copying();
+        if self.draining() {
+            if self.array.size > 0 {
+                // keep holding the final frame until everything is popped
+                self.mark_limit_reached();
+                if self.array.old_data_appended == self.array.old_data_size {
+                    self.pop();
+                }
+            }
+        } else if !self.limited_reached && self.script_waits() {
+            // only the copy goes on, tick counts the wait down
+        } else if !self.limited_reached && !blocked && self.script_pops() {
+            // like draining, a pop waits for the copy so it doesn't take away old data that's still being copied
+            if !self.copying() {
+                self.pop();
+                self.advance_script();
+                operations += 1;
+            }
+        } else if !self.limited_reached && !blocked && self.reserve_due() {
+            // a reserve moves everything like a resize, so it waits for the copy just the same
+            if !self.copying() {
+                operations += self.reserve();
+            }
+        } else if !self.limited_reached && !blocked {
+            if !self.growth_schedule.is_empty() {
+                self.apply_growth_schedule();
+            }
+            let copies = self.array.copy_operations;
+            let (old_capacity, inplace_resizes) = (self.array.capacity, self.array.inplace_resizes);
+            let stop_at = self.target.or(if self.array.hard_limit.is_none() { Some(self.visible_cells) } else { None });
+            // the last chunk only gets what's left, so a run still ends right at its limit
+            let room = stop_at.into_iter().chain(self.array.hard_limit).min().map_or(usize::MAX, |limit| limit.saturating_sub(self.array.size));
+            let chunk = self.bulk.map_or(1, |bulk| bulk.min(room).max(1));
+            let (vec, rng, inplace_prob, tiers) = (&mut self.vec, &mut self.rng, self.inplace_prob, &self.tiers);
+            let mut resize = |array: &mut Array, needed: usize| {
+                if let Some(vec) = vec.as_mut() {
+                    vec.resize(needed, 0);
+                    info!("Vec reallocated");
+                    array.extend_to(vec.capacity());
+                } else if inplace_prob > 0.0 && rng.gen_bool(inplace_prob) {
+                    info!("Expanding array's capacity in place");
+                    array.extend_in_place_to(tiered_capacity(array, tiers, needed));
+                } else {
+                    info!("Expanding array's capacity by allocating more memory");
+                    array.extend_to(tiered_capacity(array, tiers, needed));
+                }
+                info!("New capacity: {}", array.capacity);
+            };
+            let appended = match self.bulk {
+                Some(_) => self.array.grow_by_with(chunk, &mut resize),
+                None => self.array.append_with(|array| resize(array, array.size + 1)),
+            };
+            match appended {
+                Err(_) => {
+                    // at the hard limit (or a fixed capacity), resizing wouldn't make any room
+                    self.limited_reached = true;
+                    self.mark_limit_reached();
+                }
+                Ok(Append::Waiting) => {}
+                Ok(Append::Resized(address)) => {
+                    self.record_resize(old_capacity, self.array.inplace_resizes > inplace_resizes);
+                    self.check_efficiency();
+                    self.last_write = Some(address - 1);
+                    self.appends += chunk;
+                    self.advance_script();
+                    // counting what an instant copy did as part of the resize
+                    operations += 1 + chunk + self.array.copy_operations - copies;
+                }
+                Ok(Append::Appended(address)) => {
+                    self.last_write = Some(address - 1);
+                    self.appends += chunk;
+                    self.advance_script();
+                    if let Some(vec) = self.vec.as_mut() {
+                        // there's room for it, so this never reallocates
+                        vec.resize(self.array.size, 0);
+                    }
+                    progress!("Successfully appended new data: {}", self.array.size);
+                    operations += chunk;
+                }
+            }
+            if stop_at.is_some_and(|stop_at| self.array.size >= stop_at) {
+                self.limited_reached = true;
+                self.mark_limit_reached();
+            }
+        }
+
+        for _ in 0..self.copies_per_frame.unwrap_or(1) {
+            match self.array.append_old_data() {
+                Ok(copied) => self.last_copy = Some(copied - 1),
+                Err(_) => break,
+            }
+            if !self.limited_reached {
+                debuggery!("\rSuccessfully appended old data: {}", self.array.old_data_appended);
+                operations += 1;
+            }
+        }
+
+        self.memory_efficiency = self.array.memory_efficiency();
+        self.peak_capacity = self.peak_capacity.max(self.array.capacity);
+        self.total_operations += operations as u64;
+        if !self.limited_reached {
+            self.operations_per_append = operations as f64;
+            self.all_appends.push(self.operations_per_append);
+            self.all_efficiencies.push(self.memory_efficiency);
+            self.all_capacities.push(self.array.capacity as f64);
+            self.all_sizes.push(self.array.size as f64);
+            let effective_size = self.array.size + self.array.old_data_appended - self.array.old_data_size;
+            self.wasted_slots += (self.array.capacity - effective_size) as u64;
+        }
+    }
+
+    /// Capacities of the blocks given up by the resizes so far, oldest first. The current one isn't included,
+    /// and neither are the ones that grew in place since those are still in use
+    pub fn previous_allocations(&self) -> Vec<usize> {
+        self.resize_events.iter().filter(|event| !event.in_place && event.old_capacity > 0).map(|event| event.old_capacity).collect()
+    }
+
+    /// Bytes of every block in [`Simulation::previous_allocations`], all of it back with the allocator for reuse
+    pub fn freed_bytes(&self) -> usize {
+        self.previous_allocations().iter().sum::<usize>() * self.array.element_size
+    }
+
+    /// Share of all the memory the array got from the allocator that it gave back as freed blocks, a rough
+    /// measure of the holes it leaves in the heap. Resizes in place don't leave any, so it drops as they get likelier
+    pub fn fragmentation(&self) -> f64 {
+        let freed = self.freed_bytes();
+        if freed == 0 {
+            return 0.0;
+        }
+        freed as f64 / (freed + self.array.allocated_bytes()) as f64
+    }
+
+    /// Resizes it takes a growth factor of `g` to make room for the current size, `ceil(log(size / initial) / log(g))`.
+    /// [`None`] when the capacity doesn't follow a single growth factor (a `Vec`, a growth schedule or tiers)
+    pub fn predicted_resizes(&self) -> Option<usize> {
+        if self.vec_backed() || !self.growth_schedule.is_empty() || !self.tiers.is_empty() || self.array.growth <= 1.0 {
+            return None;
+        }
+        let size = self.array.size;
+        // an empty array needs one resize before it has room for anything
+        let (start, initial) = if self.initial_capacity == 0 { (1, 1) } else { (0, self.initial_capacity) };
+        if size <= initial {
+            return Some(if size == 0 { 0 } else { start });
+        }
+        Some(start + ((size as f64 / initial as f64).ln() / self.array.growth.ln()).ceil() as usize)
+    }
+
+    /// Copy operations per appended element so far, see [`Array::theoretical_copies_per_append`] for where it's headed.
+    /// That's also the write overhead, the writes on top of putting every element in once
+    pub fn copies_per_append(&self) -> f64 {
+        self.array.copy_operations as f64 / self.appends.max(1) as f64
+    }
+
+    /// Elements written into memory so far, every append once and every copy once more
+    pub fn total_writes(&self) -> usize {
+        self.appends + self.array.copy_operations
+    }
+
+    /// Puts the array into the state recorded in `row` instead of simulating a tick.
+    /// Running out of rows counts as reaching the limit
+    pub fn replay(&mut self, row: Option<&metrics::Row>) {
+        self.ticks += 1;
+        self.last_write = None;
+        self.last_copy = None;
+        let Some(row) = row else {
+            self.record_copies(0);
+            if !self.limited_reached {
+                self.limited_reached = true;
+                self.mark_limit_reached();
+            }
+            return;
+        };
+        self.last_write = (row.size > self.array.size).then(|| row.size - 1);
+        self.appends += row.size.saturating_sub(self.array.size);
+        self.last_copy = (row.old_data_appended > 0 && row.old_data_appended != self.array.old_data_appended).then(|| row.old_data_appended - 1);
+        self.record_copies(row.copy_operations.saturating_sub(self.array.copy_operations));
+        if row.resizes > self.array.resizes {
+            let old_capacity = self.array.capacity;
+            // the CSV doesn't say, but only a resize in place has nothing to copy while holding elements
+            let in_place = row.old_data_size == 0 && self.array.size > 0;
+            self.array.capacity = row.capacity;
+            self.array.old_data_size = row.old_data_size;
+            self.record_resize(old_capacity, in_place);
+            self.array.worst_case_append = self.array.worst_case_append.max(row.old_data_size + 2);
+        }
+        self.array.size = row.size;
+        self.array.capacity = row.capacity;
+        self.array.old_data_size = row.old_data_size;
+        self.array.old_data_appended = row.old_data_appended;
+        self.array.resizes = row.resizes;
+        self.array.copy_operations = row.copy_operations;
+        self.memory_efficiency = row.memory_efficiency;
+        self.operations_per_append = row.operations_per_append;
+        self.total_operations += row.operations_per_append as u64;
+        self.peak_capacity = self.peak_capacity.max(row.capacity);
+        self.all_appends.push(row.operations_per_append);
+        self.all_efficiencies.push(row.memory_efficiency);
+        self.all_capacities.push(row.capacity as f64);
+        self.all_sizes.push(row.size as f64);
+        self.wasted_slots += (row.capacity - (row.size + row.old_data_appended - row.old_data_size)) as u64;
+    }
+
+    /// Appends left until the hard limit (or the target when it comes first) and the operations they're
+    /// estimated to take going by the average so far. Stays at zero once the limit is reached, [`None`] when there's no limit
+    pub fn remaining_to_limit(&self) -> Option<(usize, f64)> {
+        let limit = match (self.array.hard_limit, self.target) {
+            (Some(limit), Some(target)) => limit.min(target),
+            (limit, target) => limit.or(target)?,
+        };
+        if self.limited_reached {
+            return Some((0, 0.0));
+        }
+        let remaining = limit.saturating_sub(self.array.size);
+        let average = if self.all_appends.is_empty() { 1.0 } else { stats::mean(&self.all_appends) };
+        Some((remaining, remaining as f64 * average))
+    }
+
+    /// Appends until the next resize and the capacity it's going to grow into, [`None`] once the array can't grow anymore.
+    /// A real `Vec` decides its next capacity itself, so that's only known once it happens
+    pub fn next_resize(&self) -> Option<(usize, Option<usize>)> {
+        if self.limited_reached || !self.array.can_extend() {
+            return None;
+        }
+        let capacity = (!self.vec_backed()).then(|| tiered_capacity(&self.array, &self.tiers, 0));
+        Some((self.array.capacity - self.array.size, capacity))
+    }
+
+    /// Whether the limit was reached and the array is just being held for a bit before the run finishes
+    pub fn holding(&self) -> bool {
+        self.limited_reached && !(self.draining() && self.array.size > 0)
+    }
+
+    /// Whether the limit was reached and the final state has been shown for long enough
+    pub fn finished(&self) -> bool {
+        self.limited_reached
+            && if self.deterministic {
+                (self.ticks - self.limit_tick) as f64 >= self.hold_seconds * TICKS_PER_SECOND as f64
+            } else {
+                self.last_limit_reached.elapsed().as_secs_f64() >= self.hold_seconds
+            }
+    }
+
+    pub fn summary(&self, palette: &str) -> stats::RunSummary {
+        let stats = stats::FinalStats {
+            growth: self.array.growth,
+            palette: palette.to_string(),
+            capacity: self.array.capacity,
+            size: self.array.size,
+            resizes: self.array.resizes,
+            inplace_resizes: self.array.inplace_resizes,
+            seed: self.seed,
+            shrinks: self.array.shrinks,
+            copy_operations: self.array.copy_operations,
+            worst_case_append: self.array.worst_case_append,
+            mean_efficiency: stats::mean(&self.all_efficiencies),
+            copy_amplification: self.array.copy_amplification(),
+            estimated_copy_ms: self.array.estimated_copy_time().map(|time| time.as_secs_f64() * 1000.0),
+            mean_operations_per_append: stats::mean(&self.all_appends),
+            total_writes: self.total_writes(),
+            write_overhead: self.copies_per_append(),
+            peak_capacity: self.peak_capacity,
+            wasted_slots: self.wasted_slots,
+        };
+        stats::RunSummary {
+            stats,
+            all_efficiencies: self.all_efficiencies.clone(),
+            all_appends: self.all_appends.clone(),
+            resize_sizes: self.resize_sizes.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array::CopyModel;
+
+    #[test]
+    fn batching_keeps_per_append_operations() {
+        let mut single = Simulation::new(Array::new(2.0, Some(500)));
+        let mut batched = Simulation::new(Array::new(2.0, Some(500))).with_appends_per_frame(4);
+        for _ in 0..400 {
+            single.tick();
+        }
+        for _ in 0..100 {
+            batched.tick();
+        }
+        assert_eq!(single.all_appends, batched.all_appends);
+        assert_eq!(single.array, batched.array);
+        assert_eq!(single.total_operations, single.all_appends.iter().sum::<f64>() as u64);
+        assert_eq!(single.total_operations, batched.total_operations);
+    }
+
+    #[test]
+    fn vec_backend_follows_std_reallocations() {
+        let mut sim = Simulation::new(Array::new(2.0, None)).with_vec_backend().with_visible_cells(5000);
+        while !sim.limited_reached {
+            sim.tick();
+        }
+        let mut vec = Vec::<u8>::new();
+        let mut expected = vec![];
+        for i in 0..5000 {
+            let capacity = vec.capacity();
+            vec.push(i as u8);
+            if vec.capacity() != capacity {
+                expected.push(vec.capacity());
+            }
+        }
+        assert_eq!(sim.resize_sizes, expected);
+        assert_eq!(sim.array.capacity, vec.capacity());
+    }
+
+    #[test]
+    fn cursors_follow_the_writes() {
+        let mut sim = Simulation::new(Array::new(2.0, None)).with_copies_per_frame(Some(1));
+        sim.tick();
+        assert_eq!((sim.last_write, sim.last_copy), (Some(0), None));
+        sim.tick();
+        // the second append resizes, the old element gets copied before the new one goes in
+        assert_eq!(sim.last_copy, Some(0));
+        while sim.copying() {
+            sim.tick();
+        }
+        assert_eq!(sim.last_write, Some(sim.array.size - 1));
+    }
+
+    #[test]
+    fn random_arrivals_keep_their_mean() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        for mean in [0.5, 4.0, 100.0] {
+            let draws = (0..10_000).map(|_| poisson(&mut rng, mean) as f64).collect::<Vec<_>>();
+            assert!((stats::mean(&draws) - mean).abs() < 0.05 * mean, "mean of {} drew {}", mean, stats::mean(&draws));
+        }
+        let run = || {
+            let mut sim = Simulation::new(Array::new(2.0, None)).with_insert_rate(Some(2.0), 42);
+            for _ in 0..200 {
+                sim.tick();
+            }
+            sim.array
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn previous_allocations_leave_out_the_current_block() {
+        let mut sim = Simulation::new(Array::new(2.0, None));
+        assert!(sim.previous_allocations().is_empty());
+        for _ in 0..5 {
+            sim.tick();
+        }
+        assert_eq!(sim.previous_allocations(), vec![1, 2, 4]);
+        assert_eq!(sim.freed_bytes(), 7);
+        assert_eq!(sim.array.capacity, 8);
+        assert_eq!(sim.fragmentation(), 7.0 / 15.0);
+    }
+
+    #[test]
+    fn resizes_in_place_dont_fragment() {
+        let fragmentation = |inplace_prob: f64| {
+            let mut sim = Simulation::new(Array::new(2.0, None)).with_inplace_prob(inplace_prob, 7).with_target(Some(5000));
+            while !sim.limited_reached {
+                sim.tick();
+            }
+            sim.fragmentation()
+        };
+        assert_eq!(fragmentation(1.0), 0.0);
+        assert!(fragmentation(0.5) < fragmentation(0.0));
+    }
+
+    #[test]
+    fn invariants_hold_every_tick() {
+        let script = parse_script("append 300, pop 250, append 40, pop 80").unwrap();
+        let sims = [
+            Simulation::new(Array::new(1.5, Some(2000)).with_shrink_threshold(Some(4.0))),
+            Simulation::new(Array::new(2.0, None)).with_inplace_prob(0.5, 3).with_visible_cells(3000),
+            Simulation::new(Array::new(2.0, None).with_copy_model(CopyModel::Instant)).with_appends_per_frame(7).with_visible_cells(3000),
+            Simulation::new(Array::new(1.618, None).with_shrink_threshold(Some(2.0))).with_copies_per_frame(Some(3)).with_script(Some(script)),
+            Simulation::new(Array::new(2.0, None)).with_vec_backend().with_visible_cells(3000),
+        ];
+        for mut sim in sims {
+            for _ in 0..10_000 {
+                sim.tick();
+                if let Err(e) = sim.check_invariants() {
+                    panic!("tick {}: {}\n{:#?}", sim.ticks, e, sim.array);
+                }
+            }
+            assert!(sim.array.copy_operations > 0);
+        }
+    }
+
+    #[test]
+    fn bulk_appends_need_fewer_resizes() {
+        let run = |bulk: Option<usize>| {
+            let mut sim = Simulation::new(Array::new(1.5, None)).with_bulk(bulk).with_visible_cells(5000);
+            while !sim.limited_reached {
+                sim.tick();
+                sim.check_invariants().unwrap();
+            }
+            sim
+        };
+        let (single, bulk) = (run(None), run(Some(300)));
+        // the last chunk is cut short to end right at the grid
+        assert_eq!((single.array.size, bulk.array.size), (5000, 5000));
+        assert_eq!(bulk.appends, 5000);
+        assert!(bulk.array.resizes < single.array.resizes);
+    }
+
+    #[test]
+    fn doubling_about_doubles_the_writes() {
+        let writes = |target: usize| {
+            let mut sim = Simulation::new(Array::new(2.0, None).with_copy_model(CopyModel::Instant)).with_target(Some(target));
+            while !sim.limited_reached {
+                sim.tick();
+            }
+            (sim.total_writes(), sim.copies_per_append())
+        };
+        // filled right up to a power of two every element got copied about once
+        assert_eq!(writes(1024), (1024 + 1023, 1023.0 / 1024.0));
+        // one past it everything just got copied again
+        assert_eq!(writes(1025), (1025 + 2047, 2047.0 / 1025.0));
+    }
+
+    #[test]
+    fn reserving_ahead_saves_resizes() {
+        let run = |reserve_at: Vec<(usize, usize)>| {
+            let mut sim = Simulation::new(Array::new(2.0, None)).with_reserve_at(reserve_at).with_target(Some(3000));
+            while !sim.limited_reached {
+                sim.tick();
+                sim.check_invariants().unwrap();
+            }
+            sim
+        };
+        let (plain, reserved) = (run(vec![]), run(vec![(10, 2990)]));
+        assert_eq!(reserved.array.capacity, 3000);
+        assert_eq!(reserved.resize_events.last().unwrap().new_capacity, 3000);
+        assert!(reserved.array.resizes < plain.array.resizes);
+        assert!(reserved.array.copy_operations < plain.array.copy_operations);
+    }
+
+    #[test]
+    fn step_scripts_wait_whole_ticks() {
+        let script = parse_script("# fill up a bit\npush 5\n\nwait 3\npop 2").unwrap();
+        assert_eq!(script, vec![(Operation::Append, 5), (Operation::Wait, 3), (Operation::Pop, 2)]);
+        let mut sim = Simulation::new(Array::new(2.0, None)).with_appends_per_frame(2).with_script(Some(script));
+        let mut sizes = vec![];
+        while !sim.limited_reached {
+            sim.tick();
+            sizes.push(sim.array.size);
+        }
+        // the second step of the third tick runs into the wait without it counting, then three ticks of nothing
+        assert_eq!(sizes, vec![2, 4, 5, 5, 5, 5, 3]);
+        assert!(parse_script("wait").unwrap_err().contains("wait <ticks>"));
+    }
+
+    #[test]
+    fn scripts_append_and_pop() {
+        assert_eq!(parse_script("append 100, pop 30,append 200"), Ok(vec![(Operation::Append, 100), (Operation::Pop, 30), (Operation::Append, 200)]));
+        assert!(parse_script("append").is_err());
+        assert!(parse_script("insert 5").is_err());
+        assert!(parse_script("pop 1 2").is_err());
+
+        let script = parse_script("append 10, pop 4, append 2").unwrap();
+        let mut sim = Simulation::new(Array::new(2.0, None)).with_script(Some(script));
+        while !sim.limited_reached {
+            sim.tick();
+        }
+        assert_eq!(sim.array.size, 8);
+        assert_eq!(sim.appends, 12);
+        assert_eq!(sim.array.capacity, 16);
+        // the last two pops got overwritten by the appends after them
+        assert_eq!(sim.recently_removed().map(|(index, _)| index).collect::<Vec<_>>(), vec![9, 8]);
+    }
+
+    #[test]
+    fn growth_schedule_switches_factors_by_size() {
+        let mut sim = Simulation::new(Array::new(2.0, None)).with_growth_schedule(vec![(0, 2.0), (8, 1.5)]).with_target(Some(20));
+        while !sim.limited_reached {
+            sim.tick();
+        }
+        assert_eq!(sim.resize_sizes, vec![2, 4, 8, 12, 18, 27]);
+        assert_eq!(sim.array.growth, 1.5);
+    }
+
+    #[test]
+    fn next_resize_counts_down() {
+        let mut sim = Simulation::new(Array::new(2.0, Some(16)).with_initial_capacity(4));
+        assert_eq!(sim.next_resize(), Some((4, Some(8))));
+        sim.tick();
+        assert_eq!(sim.next_resize(), Some((3, Some(8))));
+        while sim.array.capacity < 16 {
+            sim.tick();
+        }
+        // the limit doesn't leave any room for another one
+        assert_eq!(sim.next_resize(), None);
+    }
+
+    #[test]
+    fn doubling_resizes_as_predicted() {
+        let mut sim = Simulation::new(Array::new(2.0, None));
+        assert_eq!(sim.predicted_resizes(), Some(0));
+        for _ in 0..1000 {
+            sim.tick();
+            assert_eq!(sim.predicted_resizes(), Some(sim.array.resizes), "at size {}", sim.array.size);
+        }
+        let mut sim = Simulation::new(Array::new(2.0, None).with_initial_capacity(0));
+        for _ in 0..100 {
+            sim.tick();
+            assert_eq!(sim.predicted_resizes(), Some(sim.array.resizes), "at size {}", sim.array.size);
+        }
+    }
+
+    #[test]
+    fn huge_factors_get_flagged() {
+        let mut sim = Simulation::new(Array::new(100.0, None));
+        sim.tick();
+        assert!(!sim.low_efficiency_flash());
+        // the second element goes into a block of 100
+        sim.tick();
+        assert!(sim.low_efficiency_flash());
+        for _ in 0..LOW_EFFICIENCY_FLASH_TICKS {
+            sim.tick();
+        }
+        assert!(!sim.low_efficiency_flash());
+
+        let mut sim = Simulation::new(Array::new(2.0, None));
+        for _ in 0..100 {
+            sim.tick();
+            assert!(!sim.low_efficiency_flash());
+        }
+    }
+
+    #[test]
+    fn flagging_starts_right_below_a_tenth() {
+        // the resize out of the first slot leaves 2 elements in `growth` slots
+        let second_element = |growth: f64| {
+            let mut sim = Simulation::new(Array::new(growth, None));
+            sim.tick();
+            sim.tick();
+            assert_eq!((sim.array.size, sim.array.capacity), (2, growth as usize));
+            sim.low_efficiency_flash()
+        };
+        // exactly 10%
+        assert!(!second_element(20.0));
+        // 2 of 21 slots, 9.5%
+        assert!(second_element(21.0));
+    }
+
+    #[test]
+    fn tiers_round_resizes_up() {
+        let mut sim = Simulation::new(Array::new(2.0, None)).with_tiers(vec![256, 16]).with_target(Some(600));
+        while !sim.limited_reached {
+            sim.tick();
+        }
+        assert_eq!(sim.resize_sizes, vec![16, 256, 512, 1024]);
+        let mut sim = Simulation::new(Array::new(2.0, Some(100))).with_tiers(vec![16, 256]);
+        while !sim.limited_reached {
+            sim.tick();
+        }
+        assert_eq!(sim.resize_sizes, vec![16, 100]);
+    }
+
+    #[test]
+    fn target_stops_past_the_visible_grid() {
+        let mut sim = Simulation::new(Array::new(2.0, None)).with_visible_cells(100).with_target(Some(300));
+        while !sim.limited_reached {
+            sim.tick();
+        }
+        assert_eq!(sim.array.size, 300);
+        assert_eq!(sim.remaining_to_limit(), Some((0, 0.0)));
+    }
+
+    #[test]
+    fn copy_rate_spikes_after_a_resize() {
+        let mut sim = Simulation::new(Array::new(2.0, None)).with_copies_per_frame(Some(4));
+        while sim.array.capacity < 64 {
+            sim.tick();
+        }
+        // the resize to 64 has 32 elements to copy, 4 per tick
+        assert_eq!(sim.recent_copies.back(), Some(&4));
+        assert!(sim.copy_rate() > 0.0);
+        while sim.copying() {
+            sim.tick();
+        }
+        for _ in 0..20 {
+            sim.tick();
+        }
+        assert!(sim.recent_copies.iter().rev().take(20).all(|&copies| copies == 0));
+        assert!(sim.recent_copies.len() <= RECENT_TICKS);
+    }
+
+    #[test]
+    fn elapsed_stops_at_the_limit() {
+        let mut sim = Simulation::new(Array::new(2.0, Some(8))).with_deterministic(true);
+        while !sim.limited_reached {
+            sim.tick();
+        }
+        let (ticks, time) = sim.elapsed();
+        assert_eq!(time.as_secs_f64(), ticks as f64 / TICKS_PER_SECOND as f64);
+        for _ in 0..10 {
+            sim.tick();
+        }
+        assert_eq!(sim.elapsed(), (ticks, time));
+        assert_eq!(sim.appends_per_second(), 8.0 / time.as_secs_f64());
+    }
+
+    #[test]
+    fn mean_efficiency_stays_near_the_analytic_bound() {
+        let mean_efficiency = |growth: f64, copy_model: CopyModel| {
+            let mut sim = Simulation::new(Array::new(growth, None).with_copy_model(copy_model)).with_target(Some(100_000));
+            while !sim.limited_reached {
+                sim.tick();
+            }
+            stats::mean(&sim.all_efficiencies)
+        };
+        for growth in [1.5, 1.618, 2.0] {
+            // filling from capacity / growth up to capacity averages (1 + 1 / growth) / 2, a bit less when the run stops part way into a block
+            let bound = (1.0 + 1.0 / growth) / 2.0;
+            let instant = mean_efficiency(growth, CopyModel::Instant);
+            assert!((bound - 0.06..=bound + 0.02).contains(&instant), "growth {} averaged {}, expected about {}", growth, instant, bound);
+            // copying one element per tick leaves the new block emptier until the copy catches up
+            let incremental = mean_efficiency(growth, CopyModel::Incremental);
+            assert!(incremental < instant && incremental > bound / 2.0, "growth {} averaged {} incrementally", growth, incremental);
+        }
+    }
+}
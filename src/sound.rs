@@ -0,0 +1,75 @@
+/// Short beeps on resizes, played through SDL's audio callback so the render loop never waits on it
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::AudioSubsystem;
+
+/// Length of a beep in seconds
+const BEEP_SECONDS: f32 = 0.08;
+const VOLUME: f32 = 0.25;
+
+/// The tone being played, filled in by [`Sound::beep`] and played out by SDL's audio thread
+pub struct Tone {
+    sample_rate: f32,
+    frequency: f32,
+    phase: f32,
+    /// Samples left of the current beep and how many it had, for fading it out so it doesn't click
+    remaining: usize,
+    length: usize,
+}
+
+impl AudioCallback for Tone {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            if self.remaining == 0 {
+                *sample = 0.0;
+                continue;
+            }
+            let fade = self.remaining as f32 / self.length as f32;
+            *sample = (self.phase * std::f32::consts::TAU).sin() * VOLUME * fade;
+            self.phase = (self.phase + self.frequency / self.sample_rate) % 1.0;
+            self.remaining -= 1;
+        }
+    }
+}
+
+pub struct Sound {
+    device: AudioDevice<Tone>,
+}
+
+/// Pitch of the beep for a resize into `capacity`, going up a whole tone every time the capacity doubles
+fn pitch(capacity: usize) -> f32 {
+    (220.0 * 2f32.powf((capacity.max(1) as f32).log2() / 6.0)).min(2000.0)
+}
+
+impl Sound {
+    pub fn new(audio: &AudioSubsystem) -> Result<Self, String> {
+        let desired = AudioSpecDesired {
+            freq: Some(44_100),
+            channels: Some(1),
+            samples: None,
+        };
+        let device = audio.open_playback(None, &desired, |spec| Tone {
+            sample_rate: spec.freq as f32,
+            frequency: 0.0,
+            phase: 0.0,
+            remaining: 0,
+            length: 1,
+        })?;
+        device.resume();
+        Ok(Self { device })
+    }
+
+    /// Starts a beep for a resize into `capacity`, cutting off the one still playing
+    pub fn beep(&mut self, capacity: usize) {
+        let mut tone = self.device.lock();
+        tone.frequency = pitch(capacity);
+        tone.length = (tone.sample_rate * BEEP_SECONDS) as usize;
+        tone.remaining = tone.length;
+    }
+
+    /// Stops playback and closes the audio device
+    pub fn close(self) {
+        self.device.pause();
+    }
+}
@@ -0,0 +1,160 @@
+/// Machine readable statistics of a finished run
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+
+/// Average of `values`, [`f64::NAN`] when there's nothing to average
+pub fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Counts `sizes` into power of two buckets, bucket `i` holding the sizes in `2^i..2^(i+1)`.
+/// Anything past the last of the `max_buckets` buckets gets counted into it
+pub fn log2_buckets(sizes: &[usize], max_buckets: usize) -> Vec<usize> {
+    let mut buckets = vec![];
+    for &size in sizes {
+        let bucket = (size.max(1).ilog2() as usize).min(max_buckets - 1);
+        if buckets.len() <= bucket {
+            buckets.resize(bucket + 1, 0);
+        }
+        buckets[bucket] += 1;
+    }
+    buckets
+}
+
+/// Everything worth keeping from a run once the window closes
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    #[serde(flatten)]
+    pub stats: FinalStats,
+    pub all_efficiencies: Vec<f64>,
+    pub all_appends: Vec<f64>,
+    /// Capacity after every resize
+    pub resize_sizes: Vec<usize>,
+}
+
+/// The numbers a run ended with, a [`RunSummary`] without the series that grow with its length
+#[derive(Debug, Serialize)]
+pub struct FinalStats {
+    pub growth: f64,
+    /// Cell palette the run was drawn with
+    pub palette: String,
+    pub capacity: usize,
+    pub size: usize,
+    pub resizes: usize,
+    pub inplace_resizes: usize,
+    pub seed: u64,
+    pub shrinks: usize,
+    pub copy_operations: usize,
+    pub worst_case_append: usize,
+    pub peak_capacity: usize,
+    /// Sum of the unused slots over every append, the wasted space integrated over time
+    pub wasted_slots: u64,
+    pub mean_efficiency: f64,
+    /// Bytes ever copied per byte stored at the end
+    pub copy_amplification: f64,
+    /// Milliseconds the copies would've taken at `--element-cost`
+    pub estimated_copy_ms: Option<f64>,
+    pub mean_operations_per_append: f64,
+    /// Elements written, the appends plus every copy
+    pub total_writes: usize,
+    /// Writes on top of the appends per append, around 1 for doubling
+    pub write_overhead: f64,
+}
+
+/// An output file of a run as listed in `manifest.json`, with what it was made with
+#[derive(Debug, Serialize)]
+pub struct ManifestEntry<'a> {
+    pub file: String,
+    /// Seconds since the Unix epoch when the run finished
+    pub timestamp: u64,
+    /// Growth factor of every array shown, one unless comparing
+    pub growths: Vec<f64>,
+    pub grid_width: usize,
+    pub grid_height: usize,
+    pub runs: Vec<&'a FinalStats>,
+}
+
+/// Adds `entry` to the `manifest.json` in `dir`, a JSON array of every output written there.
+/// The manifest gets created when there's none yet. It's replaced through a temporary file so it's never left
+/// half written, but two runs finishing at once in the same directory can still drop one of their entries
+pub fn append_manifest(dir: &Path, entry: &ManifestEntry) -> std::io::Result<()> {
+    let path = dir.join("manifest.json");
+    let mut entries: Vec<serde_json::Value> = match std::fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{} isn't a JSON array ({})", path.display(), e)))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(e),
+    };
+    entries.push(serde_json::to_value(entry)?);
+    let temporary = dir.join(format!("manifest.json.{}.tmp", std::process::id()));
+    write_json(&entries, Some(&temporary))?;
+    std::fs::rename(&temporary, &path)
+}
+
+/// Writes `value` as JSON into `path`, or into stdout when there's no path
+fn write_json(value: &impl Serialize, path: Option<&Path>) -> std::io::Result<()> {
+    match path {
+        Some(path) => {
+            let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            serde_json::to_writer_pretty(&mut file, value)?;
+            file.flush()?;
+        }
+        None => write_json_to(value, &mut std::io::stdout().lock())?,
+    }
+    Ok(())
+}
+
+/// Writes `value` as JSON into `out` with a newline after it, and nothing else
+fn write_json_to(value: &impl Serialize, out: &mut impl Write) -> std::io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, value)?;
+    writeln!(out)
+}
+
+/// Writes the summaries as JSON. A single run is written as a plain object,
+/// several runs (comparison mode) as an array of them in the order they were given
+pub fn write_summaries(summaries: &[RunSummary], path: Option<&str>) -> std::io::Result<()> {
+    match summaries {
+        [summary] => write_json(summary, path.map(Path::new)),
+        _ => write_json(&summaries, path.map(Path::new)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_are_powers_of_two() {
+        assert_eq!(log2_buckets(&[], 4), Vec::<usize>::new());
+        assert_eq!(log2_buckets(&[1, 2, 3, 4, 7, 8], 8), vec![1, 2, 2, 1]);
+        // doubling puts exactly one resize into every bucket
+        assert_eq!(log2_buckets(&[2, 4, 8, 16, 32], 8), vec![0, 1, 1, 1, 1, 1]);
+        assert_eq!(log2_buckets(&[1, 1000, 5000], 3), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn stdout_gets_nothing_but_the_json() {
+        let entry = ManifestEntry { file: "2.mp4".to_string(), timestamp: 0, growths: vec![2.0], grid_width: 100, grid_height: 100, runs: vec![] };
+        let mut out = vec![];
+        write_json_to(&entry, &mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["file"], "2.mp4");
+        assert!(out.ends_with(b"}\n"));
+    }
+
+    #[test]
+    fn manifests_get_appended_to() {
+        let dir = std::env::temp_dir().join(format!("manifest-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["1.5.mp4", "2.mp4"] {
+            let entry = ManifestEntry { file: file.to_string(), timestamp: 0, growths: vec![file.trim_end_matches(".mp4").parse().unwrap()], grid_width: 100, grid_height: 100, runs: vec![] };
+            append_manifest(&dir, &entry).unwrap();
+        }
+        let manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let files = manifest.as_array().unwrap().iter().map(|entry| entry["file"].as_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(files, ["1.5.mp4", "2.mp4"]);
+        assert_eq!(manifest[1]["growths"][0], 2.0);
+    }
+}
@@ -0,0 +1,98 @@
+/// Vector export of the memory map, rebuilt from the arrays instead of read back from the window
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+use sdl2::pixels::Color;
+
+use crate::render::{self, CellState};
+use crate::simulation::Simulation;
+use crate::theme::Theme;
+
+/// Side of a cell in SVG units
+const CELL: usize = 10;
+/// Height of the legend below the grid
+const LEGEND_HEIGHT: usize = 30;
+
+fn rgb(color: Color) -> String {
+    format!("rgb({},{},{})", color.r, color.g, color.b)
+}
+
+/// The memory map of every array in `sims` stacked in bands of `band_height` rows like on screen,
+/// with grid lines every `grid_every` cells and the legend below it
+pub fn render(sims: &[Simulation], grid_width: usize, band_height: usize, grid_every: Option<usize>, theme: &Theme) -> String {
+    let width = grid_width * CELL;
+    let grid_height = band_height * sims.len() * CELL;
+    let mut svg = String::new();
+    let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#, width, grid_height + LEGEND_HEIGHT, width, grid_height + LEGEND_HEIGHT);
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="{}"/>"#, rgb(theme.background));
+    for (band, sim) in sims.iter().enumerate() {
+        for row in 0..band_height {
+            let y = (band * band_height + row) * CELL;
+            // neighbouring cells of the same state become one rect, so a full grid isn't tens of thousands of them
+            let mut column = 0;
+            while column < grid_width {
+                let state = render::cell_state(&sim.array, row * grid_width + column, sim.limited_reached);
+                let Some(color) = render::state_color(state, &theme.palette) else { break };
+                let start = column;
+                while column < grid_width && render::cell_state(&sim.array, row * grid_width + column, sim.limited_reached) == state {
+                    column += 1;
+                }
+                let _ = writeln!(svg, r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#, start * CELL, y, (column - start) * CELL, CELL, rgb(color));
+            }
+        }
+    }
+    if let Some(every) = grid_every {
+        let lines = rgb(theme.grid_lines);
+        for x in render::grid_lines(grid_width, every) {
+            let _ = writeln!(svg, r#"<line x1="{}" y1="0" x2="{}" y2="{}" stroke="{}"/>"#, x * CELL, x * CELL, grid_height, lines);
+        }
+        let rows = band_height * sims.len();
+        for y in render::grid_lines(rows, every) {
+            let _ = writeln!(svg, r#"<line x1="0" y1="{}" x2="{}" y2="{}" stroke="{}"/>"#, y * CELL, width, y * CELL, lines);
+        }
+    }
+    for band in 1..sims.len() {
+        let y = band * band_height * CELL;
+        let _ = writeln!(svg, r#"<line x1="0" y1="{}" x2="{}" y2="{}" stroke="red" stroke-width="2"/>"#, y, width, y);
+    }
+    let mut x = 10;
+    let y = grid_height + 10;
+    for state in CellState::ALL {
+        let Some(color) = render::state_color(state, &theme.palette) else { continue };
+        let _ = writeln!(svg, r#"<rect x="{}" y="{}" width="12" height="12" fill="{}"/>"#, x, y, rgb(color));
+        let _ = writeln!(svg, r#"<text x="{}" y="{}" font-family="sans-serif" font-size="12" fill="{}">{}</text>"#, x + 16, y + 11, rgb(theme.text), state.label());
+        x += 16 + state.label().len() * 8 + 16;
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Writes [`render`] into `path`
+pub fn write(path: &str, sims: &[Simulation], grid_width: usize, band_height: usize, grid_every: Option<usize>, theme: &Theme) -> std::io::Result<()> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    file.write_all(render(sims, grid_width, band_height, grid_every, theme).as_bytes())?;
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array::Array;
+
+    #[test]
+    fn runs_of_cells_become_single_rects() {
+        let mut sim = Simulation::new(Array::new(2.0, None).with_initial_capacity(6));
+        for _ in 0..3 {
+            sim.tick();
+        }
+        let svg = render(&[sim], 4, 2, None, &Theme::LIGHT);
+        let live = rgb(Theme::LIGHT.palette.live);
+        let empty = rgb(Theme::LIGHT.palette.empty);
+        // 3 live and 1 empty cell in the first row, 2 empty in the second, plus a swatch of each in the legend
+        assert!(svg.contains(&format!(r#"<rect x="0" y="0" width="30" height="10" fill="{}"/>"#, live)));
+        assert!(svg.contains(&format!(r#"<rect x="30" y="0" width="10" height="10" fill="{}"/>"#, empty)));
+        assert!(svg.contains(&format!(r#"<rect x="0" y="10" width="20" height="10" fill="{}"/>"#, empty)));
+        assert_eq!(svg.matches("<rect").count(), 1 + 3 + CellState::ALL.len());
+        assert!(!svg.contains("<line"));
+    }
+}
@@ -0,0 +1,75 @@
+/// Colors for everything drawn on screen
+use sdl2::pixels::Color;
+
+/// Colors of the memory map cells, one per [`crate::render::CellState`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub live: Color,
+    pub old_pending: Color,
+    pub old_copying: Color,
+    pub empty: Color,
+    pub reserved: Color,
+}
+
+impl Palette {
+    pub const STANDARD: Palette = Palette {
+        live: Color::GREEN,
+        old_pending: Color::BLUE,
+        old_copying: Color::CYAN,
+        empty: Color::BLACK,
+        reserved: Color::RGB(110, 70, 0),
+    };
+
+    /// Okabe-Ito colors that stay apart for deuteranopia and protanopia. The two kinds of
+    /// old data also differ a lot in brightness since that's what's easiest to tell apart
+    pub const COLORBLIND: Palette = Palette {
+        live: Color::RGB(86, 180, 233),
+        old_pending: Color::RGB(213, 94, 0),
+        old_copying: Color::RGB(240, 228, 66),
+        empty: Color::BLACK,
+        reserved: Color::RGB(204, 121, 167),
+    };
+
+    pub fn from_name(name: &str) -> Option<Palette> {
+        match name {
+            "standard" => Some(Palette::STANDARD),
+            "colorblind" => Some(Palette::COLORBLIND),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub background: Color,
+    pub grid_lines: Color,
+    pub text: Color,
+    pub graph_background: Color,
+    pub palette: Palette,
+}
+
+impl Theme {
+    pub const LIGHT: Theme = Theme {
+        background: Color::RGB(200, 200, 200),
+        grid_lines: Color::WHITE,
+        text: Color::BLACK,
+        graph_background: Color::BLACK,
+        palette: Palette::STANDARD,
+    };
+
+    pub const DARK: Theme = Theme {
+        background: Color::RGB(24, 24, 24),
+        grid_lines: Color::RGB(70, 70, 70),
+        text: Color::RGB(230, 230, 230),
+        graph_background: Color::BLACK,
+        palette: Palette::STANDARD,
+    };
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        match name {
+            "light" => Some(Theme::LIGHT),
+            "dark" => Some(Theme::DARK),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Runs the array headless through [`Array::simulate`] and compares every step against a checked-in recording,
+//! so changes to the order of growing, resizing and copying show up even when the totals still work out.
+//! `UPDATE_GOLDEN=1 cargo test --test golden` rewrites the recording after an intended change
+use growth_size_array::array::Array;
+
+const STEPS: usize = 300;
+
+fn golden_path(name: &str) -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(name)
+}
+
+/// `size capacity resizes copy_operations` of every step, one line each
+fn trace(array: Array) -> String {
+    array
+        .simulate()
+        .take(STEPS)
+        .map(|snapshot| format!("{} {} {} {}\n", snapshot.size, snapshot.capacity, snapshot.resizes, snapshot.copy_operations))
+        .collect()
+}
+
+fn check(name: &str, array: Array) {
+    let path = golden_path(name);
+    let actual = trace(array);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("Failed to read {} ({}), UPDATE_GOLDEN=1 writes it", path.display(), e));
+    for (step, (actual, expected)) in actual.lines().zip(expected.lines()).enumerate() {
+        assert_eq!(actual, expected, "step {} of {} differs", step + 1, name);
+    }
+    assert_eq!(actual.lines().count(), expected.lines().count(), "{} has a different number of steps", name);
+}
+
+#[test]
+fn doubling_matches_the_recording() {
+    check("growth_2.0.txt", Array::new(2.0, None));
+}
+
+#[test]
+fn golden_ratio_with_a_limit_matches_the_recording() {
+    check("growth_1.5_limit_100.txt", Array::new(1.5, Some(100)));
+}